@@ -1,40 +1,101 @@
 /// Render data with TUI
+use chrono::Utc;
 use crossterm::{
     event::{Event, KeyCode, KeyModifiers},
 };
+use std::collections::HashSet;
 use std::convert::TryFrom;
-use std::io::Write;
-use std::process::{Command, Stdio};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
 use std::error;
 use tui::{
     backend::{Backend},
     layout::{Constraint, Layout},
-    style::{Modifier, Style},
-    text::Span, widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans}, widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
     Frame,
 };
-use super::app_config::{AppConfig};
+use super::app_config::{AppConfig, RunEnv};
+use super::backend::VaultBackend;
+use super::dates::DateFilter;
+use super::err::{InjectError, OpError};
+use super::export;
+use super::generator::{self, Generator, Recipe};
+use super::inject;
 use super::op;
+use super::run::{self, PendingRun};
 use super::ui;
 use super::util;
+use super::worker::{Job, Response, Worker};
 
 /// Different available views that the app can display API data
 ///
 /// - ItemListView: for looking through the list of stored data
 /// - ItemView: display all details of specific item
+/// - ItemCreateView: fill in a category template for a new item
+/// - VaultListView: pick which vault items are listed from
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AppView {
     ItemListView,
     ItemView,
+    ItemCreateView,
+    VaultListView,
 }
 
-/// Normal mode is regular operation, command is when `:` is typed
-#[derive(PartialEq)]
+/// Normal mode is regular operation, command is when `:` is typed, edit is
+/// when a field value in the item view is being changed, confirm is when a
+/// y/n popup is open, password is when an expired session needs signing in to,
+/// generate is when the password generator popup is open
+#[derive(Debug, PartialEq)]
 pub enum InputMode {
     Normal,
     Command,
-    // FIXME:
-    // Edit,
+    Edit,
+    Confirm,
+    Password,
+    Generate,
+}
+
+/// Actions that can't be undone, held onto until the y/n popup is answered
+#[derive(Debug, Clone, PartialEq)]
+pub enum Confirmation {
+    Delete { id: String, title: String },
+    Archive { id: String, title: String },
+    /// Export of the listed items with every field value in them
+    Export { path: String, format: export::Format, count: usize },
+}
+
+impl Confirmation {
+    pub fn prompt(&self) -> String {
+        match self {
+            Confirmation::Delete { title, .. } => format!("Permanently delete \"{}\"? (y/n)", title),
+            Confirmation::Archive { title, .. } => format!("Move \"{}\" to the archive? (y/n)", title),
+            Confirmation::Export { path, count, .. } => {
+                format!("Write {} item(s) to {} with every field value, secrets included? (y/n)", count, path)
+            },
+        }
+    }
+}
+
+/// What a row of the item view table shows
+#[derive(Debug, Clone, Copy)]
+pub enum ItemRow<'a> {
+    /// Overview some categories get above their fields, e.g. the end of a card number
+    Summary,
+    Field(&'a op::ItemDetailsField),
+    Url(&'a op::ItemUrl),
+    File(&'a op::ItemFile),
+    /// Heading above the fields of a labelled section, `len` of them
+    Section { section: &'a op::ItemSection, collapsed: bool, len: usize },
+}
+
+/// Feedback shown on the bottom line until the next key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Info(String),
+    Error(String),
 }
 
 #[derive(PartialEq)]
@@ -47,6 +108,17 @@ pub enum SortDirection {
 pub struct SortConfig {
     pub sort_direction: SortDirection,
     pub header: String,
+    /// Keep favorites above everything else, sorted by `header` among themselves
+    pub favorites_first: bool,
+}
+
+/// Vault, tag and category an item list was asked for. Only becomes the app's
+/// once that list comes back, a failed fetch leaves the old ones in place.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListScope {
+    pub vault: Option<String>,
+    pub tag: Option<String>,
+    pub category: Option<op::Category>,
 }
 
 #[derive(Debug)]
@@ -56,58 +128,457 @@ pub struct SearchState {
     pub selected_match_idx: Option<usize>,
 }
 
-pub struct App {
+pub struct App<V: VaultBackend> {
     pub is_running: bool,
     pub item_table_state: TableState,
     pub item_list_table_state: TableState,
     pub item_list_sort_config: SortConfig,
     pub app_view: AppView,
     pub headers: Vec<String>,
+    /// strftime format for timestamps in the item list, or `relative`
+    pub date_format: String,
     pub items: Vec<op::ItemListEntry>,
     pub item_details: Option<op::ItemDetails>,
-    pub session: op::Session,
+    /// Ids of the sections in the item view whose fields are hidden
+    pub collapsed_sections: HashSet<String>,
+    pub item_create_table_state: TableState,
+    /// Template being filled in while in `AppView::ItemCreateView`
+    pub item_template: Option<op::ItemTemplate>,
+    pub vault_list_table_state: TableState,
+    pub vaults: Vec<op::ItemDetailsVault>,
+    /// Vault items are listed from, `None` for all of them
+    pub vault: Option<String>,
+    /// Only items with this tag are listed, `None` for every item
+    pub tag: Option<String>,
+    /// Only items of this category are listed, `None` for every item
+    pub category: Option<op::Category>,
+    /// `:filter` predicates on created_at/updated_at every listed item matches
+    pub date_filters: Vec<DateFilter>,
+    pub worker: Worker<V>,
+    /// Every account the backend can switch between
+    pub accounts: Vec<String>,
+    /// Account items are listed from, `None` for all of them
+    pub account: Option<String>,
+    /// Id of the item whose details were last asked for, stale responses are dropped
+    pub pending_item_id: Option<String>,
+    /// Scope switched to whose item list hasn't come back yet
+    pub pending_scope: Option<ListScope>,
+    /// Incremented every pass of the draw loop, drives the loading indicator
+    pub tick: usize,
     pub input_mode: InputMode,
     pub cmd_input: String,
+    /// New value being typed in for the selected field while in `InputMode::Edit`
+    pub edit_input: String,
     pub search_state: Option<SearchState>,
+    pub confirmation: Option<Confirmation>,
+    /// Account whose session expired, the password popup is open while this is set
+    pub signin_account: Option<String>,
+    /// Master password being typed in while in `InputMode::Password`
+    pub password_input: String,
+    /// Why the last sign in attempt failed, shown in the password popup
+    pub signin_error: Option<String>,
+    /// Mode the password popup opened over, e.g. an edit in progress, gone back
+    /// to once it closes
+    pub mode_before_signin: Option<InputMode>,
+    /// Calls that failed on an expired session, run again once signed back in
+    pub retry_jobs: Vec<Job<V>>,
+    /// Settings of the password generator, kept between openings of the popup
+    pub generator: Option<Generator>,
+    /// Preview shown in the generator popup, what gets yanked or applied
+    pub generated: String,
+    /// Word list for passphrases, read when the generator is first opened
+    pub word_file: String,
+    /// Last confirmation or error, cleared by the next key press
+    pub message: Option<Message>,
+    /// Why the app stopped on its own, reported once the terminal is restored
+    pub exit_error: Option<OpError>,
+    /// `:run` command the draw loop should run outside of the alternate screen
+    pub pending_run: Option<PendingRun>,
+    pub run_env: RunEnv,
     pub clipboard_bin: String,
 }
 
-impl App {
-    pub fn new(config: AppConfig) -> Result<App, Box<dyn error::Error>> {
-        Ok(App {
+impl App<op::Accounts> {
+    /// Signs in to 1password the way `config.auth` says and creates an app backed
+    /// by the `op` CLI, with every account in the token file available
+    pub fn new(config: AppConfig) -> Result<Self, Box<dyn error::Error>> {
+        let token_path = format!("{}/token", config.root_dir);
+        let accounts = op::Accounts::from_auth(config.auth, token_path, &op::OpCommand::from(&config))?;
+        Ok(App::with_backend(config, accounts))
+    }
+}
+
+impl<V: VaultBackend> App<V> {
+    pub fn with_backend(config: AppConfig, session: V) -> Self {
+        let accounts = session.accounts();
+        let root_dir = &config.root_dir;
+        let word_file = config.word_file.clone().unwrap_or_else(|| format!("{}/words.txt", root_dir));
+        App {
             is_running: true,
             item_table_state: TableState::default(),
             item_list_table_state: TableState::default(),
             item_list_sort_config: SortConfig {
                 header: String::from("title"),
-                sort_direction: SortDirection::Ascending
+                sort_direction: SortDirection::Ascending,
+                favorites_first: config.favorites_first,
             },
             app_view: AppView::ItemListView,
             headers: config.headers,
+            date_format: config.date_format,
             items: Vec::new(),
             item_details: None,
-            session: op::Session::new(format!("{}/token", config.root_dir))?,
+            collapsed_sections: HashSet::new(),
+            item_create_table_state: TableState::default(),
+            item_template: None,
+            vault_list_table_state: TableState::default(),
+            vaults: Vec::new(),
+            vault: None,
+            tag: None,
+            category: None,
+            date_filters: Vec::new(),
+            worker: Worker::new(session),
+            accounts,
+            account: None,
+            pending_item_id: None,
+            pending_scope: None,
+            tick: 0,
             input_mode: InputMode::Normal,
             cmd_input: String::from(""),
+            edit_input: String::from(""),
             search_state: None,
+            confirmation: None,
+            signin_account: None,
+            password_input: String::from(""),
+            signin_error: None,
+            mode_before_signin: None,
+            retry_jobs: Vec::new(),
+            generator: None,
+            generated: String::from(""),
+            word_file,
+            message: None,
+            exit_error: None,
+            pending_run: None,
+            run_env: config.run_env,
             clipboard_bin: config.clipboard_bin,
-        })
+        }
     }
 
+    /// Fetch the item list in the background, applied once it comes back
     pub fn populate_items(&mut self) {
-        match self.session.list_items() {
+        let scope = self.scope();
+        self.worker.spawn(move |session| {
+            Response::Items(scope.clone(), session.list_items(scope.vault.as_deref()))
+        });
+    }
+
+    /// Scope of the latest item list request, the one being switched to if its
+    /// list hasn't come back yet
+    fn scope(&self) -> ListScope {
+        self.pending_scope.clone().unwrap_or_else(|| {
+            ListScope { vault: self.vault.clone(), tag: self.tag.clone(), category: self.category.clone() }
+        })
+    }
+
+    /// Fetch the item list for `scope`, switching to it once the list is back
+    fn switch_scope(&mut self, scope: ListScope) {
+        self.pending_scope = Some(scope);
+        self.populate_items();
+    }
+
+    /// Fetch the vault list in the background, the vault picker is opened once
+    /// it comes back
+    fn populate_vaults(&mut self) {
+        self.worker.spawn(|session| Response::Vaults(session.list_vaults()));
+    }
+
+    fn set_vaults(&mut self, vaults: Result<Vec<op::ItemDetailsVault>, OpError>) {
+        match vaults {
+            Ok(mut vaults) => {
+                vaults.sort_by_key(|vault| vault.name.to_lowercase());
+                let selected_i = self.vault
+                    .as_ref()
+                    .and_then(|name| vaults.iter().position(|vault| &vault.name == name))
+                    .unwrap_or(0);
+                self.vaults = vaults;
+                self.vault_list_table_state.select(Some(selected_i));
+                self.app_view = AppView::VaultListView;
+            },
+            Err(err) => self.report_error("couldn't list vaults", &err),
+        }
+    }
+
+    /// Only list items from `vault`, or from every vault if it's empty or "all"
+    fn switch_vault(&mut self, vault: String) {
+        let vault = match vault.as_str() {
+            "" | "all" => None,
+            _ => Some(vault),
+        };
+        let scope = self.scope();
+        self.switch_scope(ListScope { vault, ..scope });
+    }
+
+    /// Filter the item list down to items tagged with `tag`, `:tag all` lists
+    /// everything again. `:tag +<name>` and `:tag -<name>` add or remove a tag
+    /// on the selected or open item.
+    fn switch_tag(&mut self, tag: String) {
+        if let Some(name) = tag.strip_prefix('+') {
+            return self.edit_tags(name, true);
+        }
+        if let Some(name) = tag.strip_prefix('-') {
+            return self.edit_tags(name, false);
+        }
+        let tag = match tag.as_str() {
+            "" | "all" => None,
+            _ => Some(tag),
+        };
+        let scope = self.scope();
+        self.switch_scope(ListScope { tag, ..scope });
+    }
+
+    /// Only list items of `category`, or every item if it's empty or "all"
+    fn switch_category(&mut self, category: String) {
+        let category = match category.as_str() {
+            "" | "all" => None,
+            _ => Some(op::Category::from(category)),
+        };
+        let scope = self.scope();
+        self.switch_scope(ListScope { category, ..scope });
+    }
+
+    /// Replace the date filters with the space separated predicates in `filters`,
+    /// e.g. `updated<30d created>2022-01-01`. `all` or nothing clears them.
+    fn switch_date_filters(&mut self, filters: &str) {
+        let date_filters = match filters.trim() {
+            "" | "all" => Ok(Vec::new()),
+            filters => filters.split_whitespace().map(str::parse).collect(),
+        };
+        match date_filters {
+            Ok(date_filters) => {
+                self.date_filters = date_filters;
+                self.populate_items();
+            },
+            Err(err) => self.message = Some(Message::Error(err)),
+        }
+    }
+
+    /// Whether `item` should be listed with the current filters
+    fn is_listed(&self, item: &op::ItemListEntry) -> bool {
+        let now = Utc::now();
+        self.tag.as_ref().is_none_or(|tag| item.tags.contains(tag))
+            && self.category.as_ref().is_none_or(|category| &item.category == category)
+            && self.date_filters.iter().all(|filter| filter.matches(item, &now))
+    }
+
+    /// Add or remove `tag` with `op item edit --tags`, which replaces all of them
+    fn edit_tags(&mut self, tag: &str, add: bool) {
+        let (id, title, mut tags) = match self.app_view {
+            AppView::ItemView => match &self.item_details {
+                Some(item) => (item.id.clone(), item.title.clone(), item.tags.clone()),
+                None => return,
+            },
+            AppView::ItemListView => match self.current_item() {
+                Some(item) => (item.id.clone(), item.title.clone(), item.tags.clone()),
+                None => return,
+            },
+            AppView::ItemCreateView | AppView::VaultListView => return,
+        };
+        let tag = tag.trim();
+        // `op` splits the list on commas
+        if tag.is_empty() || tag.contains(',') {
+            self.message = Some(Message::Error(format!("\"{}\" isn't a valid tag", tag)));
+            return;
+        }
+        let has_tag = tags.iter().any(|t| t == tag);
+        let message = if add && !has_tag {
+            tags.push(String::from(tag));
+            format!("tagged \"{}\" with {}", title, tag)
+        } else if !add && has_tag {
+            tags.retain(|t| t != tag);
+            format!("removed tag {} from \"{}\"", tag, title)
+        } else {
+            let state = if add { "already" } else { "isn't" };
+            self.message = Some(Message::Info(format!("\"{}\" {} tagged {}", title, state, tag)));
+            return;
+        };
+        self.worker.spawn(move |session| Response::ItemUpdated(message.clone(), session.set_tags(&id, &tags)));
+    }
+
+    /// Add the selected or open item to favorites, or take it out
+    fn toggle_favorite(&mut self) {
+        let (id, title, favorite) = match self.app_view {
+            AppView::ItemView => match &self.item_details {
+                Some(item) => (item.id.clone(), item.title.clone(), !item.favorite),
+                None => return,
+            },
+            AppView::ItemListView => match self.current_item() {
+                Some(item) => (item.id.clone(), item.title.clone(), !item.favorite),
+                None => return,
+            },
+            AppView::ItemCreateView | AppView::VaultListView => return,
+        };
+        let message = if favorite {
+            format!("added \"{}\" to favorites", title)
+        } else {
+            format!("removed \"{}\" from favorites", title)
+        };
+        self.worker.spawn(move |session| Response::ItemUpdated(message.clone(), session.set_favorite(&id, favorite)));
+    }
+
+    /// Apply changes made to an item in the background to the list and the item view
+    fn update_item(&mut self, message: String, item_details: Result<op::ItemDetails, OpError>) {
+        let mut item_details = match item_details {
+            Ok(item_details) => item_details,
+            Err(err) => return self.report_error("couldn't update item", &err),
+        };
+        item_details.fill_none_fields();
+        let mut unlisted = false;
+        if let Some(i) = self.items.iter().position(|item| item.id == item_details.id) {
+            let item = &mut self.items[i];
+            item.version = item_details.version;
+            item.updated_at = item_details.updated_at;
+            item.tags = item_details.tags.clone();
+            item.favorite = item_details.favorite;
+            unlisted = !self.is_listed(&self.items[i]);
+            self.resort_keeping_selection();
+        }
+        // Untagged while filtering on the tag, left alone while it's still open
+        if unlisted && self.app_view != AppView::ItemView {
+            self.remove_item(&item_details.id);
+        }
+        if let Some(open) = self.item_details.as_mut().filter(|open| open.id == item_details.id) {
+            *open = item_details;
+        }
+        self.message = Some(Message::Info(message));
+    }
+
+    fn set_items(&mut self, scope: ListScope, items: Result<Vec<op::ItemListEntry>, OpError>) {
+        if self.pending_scope.as_ref() == Some(&scope) {
+            self.pending_scope = None;
+        }
+        match items {
             Ok(mut items) => {
+                self.vault = scope.vault;
+                self.tag = scope.tag;
+                self.category = scope.category;
+                items.retain(|item| self.is_listed(item));
                 for item in items.iter_mut() {
                     item.gen_index_term();
                 }
                 self.items = items;
             },
-            Err(err) => {
-                tracing::error!("Couldn't populate items: {}", err);
-                panic!("Couldn't populate items: {}", err);
-            }
+            Err(err) => self.report_error("refresh failed", &err),
         }
         self.sort_item_list();
+        let max_i = self.table_max_index(&AppView::ItemListView);
+        if self.selected_index(&AppView::ItemListView) > max_i {
+            self.item_list_table_state.select(Some(max_i));
+        }
+    }
+
+    /// True while a backend call is still running
+    pub fn is_loading(&self) -> bool {
+        self.worker.is_busy()
+    }
+
+    /// Apply any backend results that finished since the last call
+    pub fn handle_backend_responses(&mut self) {
+        while let Some((job, response)) = self.worker.try_recv() {
+            match response.error() {
+                Some(OpError::NotSignedIn(account)) => {
+                    let account = account.clone();
+                    self.retry_jobs.push(job);
+                    self.open_signin(account, None);
+                    continue;
+                },
+                // Nothing else is going to work either
+                Some(OpError::BinaryMissing) => {
+                    self.exit_error = Some(OpError::BinaryMissing);
+                    self.is_running = false;
+                    continue;
+                },
+                _ => {},
+            }
+            match response {
+                Response::Items(scope, items) => self.set_items(scope, items),
+                Response::ItemDetails(id, item_details) => {
+                    if self.pending_item_id.as_ref() == Some(&id) {
+                        self.pending_item_id = None;
+                        self.set_item_details(item_details);
+                    }
+                },
+                Response::ItemTemplate(item_template) => self.set_item_template(item_template),
+                Response::ItemCreated(item_details) => self.insert_created_item(item_details),
+                Response::Vaults(vaults) => self.set_vaults(vaults),
+                Response::Account(account) => match account {
+                    Ok(account) => {
+                        self.account = account;
+                        self.populate_items();
+                    },
+                    Err(err) => self.report_error("couldn't switch account", &err),
+                },
+                Response::ItemRemoved(id, res) => match res {
+                    Ok(_) => {
+                        if let Some(item) = self.items.iter().find(|item| item.id == id) {
+                            self.message = Some(Message::Info(format!("removed \"{}\"", item.title)));
+                        }
+                        self.remove_item(&id);
+                    },
+                    Err(err) => self.report_error("couldn't remove item", &err),
+                },
+                Response::ItemUpdated(message, item_details) => self.update_item(message, item_details),
+                Response::FileSaved(path, res) => match res {
+                    Ok(_) => self.message = Some(Message::Info(format!("saved {}", path))),
+                    Err(err) => self.report_error("couldn't save file", &err),
+                },
+                Response::Exported(path, res) => match res {
+                    Ok(n) => self.message = Some(Message::Info(format!("exported {} item(s) to {}", n, path))),
+                    Err(err) => self.report_error("couldn't export", &err),
+                },
+                Response::Injected(path, res) => match res {
+                    Ok(n) => self.message = Some(Message::Info(format!("wrote {} with {} secret(s) filled in", path, n))),
+                    Err(err) => {
+                        tracing::error!("Couldn't inject into {}: {}", path, err);
+                        self.message = Some(Message::Error(format!("couldn't inject: {}", err.summary())));
+                    },
+                },
+                Response::Signin(account, res) => match res {
+                    Ok(_) => {
+                        for job in std::mem::take(&mut self.retry_jobs) {
+                            self.worker.spawn_job(job);
+                        }
+                    },
+                    Err(err) => {
+                        tracing::error!("Couldn't sign in to {}: {}", account, err);
+                        self.open_signin(account, Some(err.summary()));
+                    },
+                },
+            }
+        }
+    }
+
+    /// Log a failed backend call and show it on the message line. Expired sessions
+    /// and a missing `op` never get here, they're dealt with as soon as the
+    /// response comes back.
+    fn report_error(&mut self, context: &str, err: &OpError) {
+        self.message = Some(Message::Error(format!("{}: {}", context, err.summary())));
+        match err {
+            OpError::NonZeroExit { code, stderr } => {
+                tracing::error!("{}: op exited with {:?}: {}", context, code, stderr)
+            },
+            // Usually means the installed `op` prints a different format than expected
+            OpError::InvalidUtf8(_) | OpError::Parse(_) => {
+                tracing::error!("{}: {}, check that `op --version` is 2.x", context, err)
+            },
+            OpError::NotSignedIn(_) | OpError::BinaryMissing | OpError::UnknownAccount(_) | OpError::Io(_) => {
+                tracing::error!("{}: {}", context, err)
+            },
+        }
+    }
+
+    /// Called once per pass of the draw loop
+    pub fn on_tick(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+        self.handle_backend_responses();
     }
 
     pub fn sort_item_list(&mut self) {
@@ -125,25 +596,64 @@ impl App {
                     SortDirection::Ascending  => |a: &op::ItemListEntry, b: &op::ItemListEntry| a.title.to_lowercase().cmp(&b.title.to_lowercase()),
                     SortDirection::Descending => |a: &op::ItemListEntry, b: &op::ItemListEntry| b.title.to_lowercase().cmp(&a.title.to_lowercase()),
                 },
+                "category" => match self.item_list_sort_config.sort_direction {
+                    SortDirection::Ascending  => |a: &op::ItemListEntry, b: &op::ItemListEntry| a.category.name().cmp(b.category.name()),
+                    SortDirection::Descending => |a: &op::ItemListEntry, b: &op::ItemListEntry| b.category.name().cmp(a.category.name()),
+                },
+                "vault" => match self.item_list_sort_config.sort_direction {
+                    SortDirection::Ascending  => |a: &op::ItemListEntry, b: &op::ItemListEntry| a.vault.name.to_lowercase().cmp(&b.vault.name.to_lowercase()),
+                    SortDirection::Descending => |a: &op::ItemListEntry, b: &op::ItemListEntry| b.vault.name.to_lowercase().cmp(&a.vault.name.to_lowercase()),
+                },
                 "updated_at" => match self.item_list_sort_config.sort_direction {
                     SortDirection::Ascending  => |a: &op::ItemListEntry, b: &op::ItemListEntry| a.updated_at.cmp(&b.updated_at),
                     SortDirection::Descending => |a: &op::ItemListEntry, b: &op::ItemListEntry| b.updated_at.cmp(&a.updated_at),
                 },
+                "created_at" => match self.item_list_sort_config.sort_direction {
+                    SortDirection::Ascending  => |a: &op::ItemListEntry, b: &op::ItemListEntry| a.created_at.cmp(&b.created_at),
+                    SortDirection::Descending => |a: &op::ItemListEntry, b: &op::ItemListEntry| b.created_at.cmp(&a.created_at),
+                },
                 &_           => |a: &op::ItemListEntry, b: &op::ItemListEntry| a.title.to_lowercase().cmp(&b.title.to_lowercase()),
             }
         );
+        if self.item_list_sort_config.favorites_first {
+            // Stable so the order within favorites and the rest is kept
+            self.items.sort_by_key(|item| !item.favorite);
+        }
+    }
+
+    fn toggle_favorites_first(&mut self) {
+        let favorites_first = !self.item_list_sort_config.favorites_first;
+        self.item_list_sort_config.favorites_first = favorites_first;
+        self.resort_keeping_selection();
+        self.message = Some(Message::Info(String::from(if favorites_first {
+            "favorites first"
+        } else {
+            "favorites sorted with everything else"
+        })));
+    }
+
+    /// Sort again without moving the highlight off the item it's on
+    fn resort_keeping_selection(&mut self) {
+        let selected_id = self.current_item().map(|item| item.id.clone());
+        self.sort_item_list();
+        if let Some(i) = selected_id.and_then(|id| self.items.iter().position(|item| item.id == id)) {
+            self.item_list_table_state.select(Some(i));
+        }
     }
 
     /// Return the index of the last element for the passed in app view
     fn table_max_index(&self, app_view: &AppView) -> usize {
         let len = match app_view {
             AppView::ItemListView => self.items.len(),
-            AppView::ItemView => self.item_details.as_ref().unwrap().fields.len(),
+            AppView::ItemView => self.item_rows().len(),
+            // Title is shown as the first row above the template fields
+            AppView::ItemCreateView => self.item_template.as_ref().map_or(0, |t| t.fields.len() + 1),
+            AppView::VaultListView => self.vaults.len(),
         };
         // Hacky just-in-case bit for always returning a usize. There should
         // always be at least one item in the list of items so `else` should
         // never be hit.
-        return if len == 0 { 0 } else { len - 1 }
+        if len == 0 { 0 } else { len - 1 }
     }
 
     /// Returns index of selected row for passed in AppView
@@ -151,15 +661,19 @@ impl App {
         match app_view {
             AppView::ItemListView => self.item_list_table_state.selected().unwrap_or(0),
             AppView::ItemView => self.item_table_state.selected().unwrap_or(0),
+            AppView::ItemCreateView => self.item_create_table_state.selected().unwrap_or(0),
+            AppView::VaultListView => self.vault_list_table_state.selected().unwrap_or(0),
         }
     }
 
     /// Set index of selected row to i for passed in AppView
     fn set_selected_index(&mut self, i: i32, app_view: &AppView) {
-        let max_i = self.table_max_index(&app_view);
+        let max_i = self.table_max_index(app_view);
         let table_state = match app_view {
             AppView::ItemListView => &mut self.item_list_table_state,
             AppView::ItemView => &mut self.item_table_state,
+            AppView::ItemCreateView => &mut self.item_create_table_state,
+            AppView::VaultListView => &mut self.vault_list_table_state,
         };
         let selected_i: usize = {
             if i < 0 {
@@ -177,21 +691,100 @@ impl App {
     /// Pass in positive i_delta to go down a row, pass in negative i_delta
     /// to go up a row.
     fn add_selected_index(&mut self, i_delta: i32, app_view: &AppView) {
-        let i_current = i32::try_from(self.selected_index(&app_view)).unwrap();
-        self.set_selected_index(i_current + i_delta, &app_view);
+        let i_current = i32::try_from(self.selected_index(app_view)).unwrap();
+        self.set_selected_index(i_current + i_delta, app_view);
     }
 
-    fn current_item(&self) -> &op::ItemListEntry {
-        &self.items[self.selected_index(&AppView::ItemListView)]
+    fn current_item(&self) -> Option<&op::ItemListEntry> {
+        self.items.get(self.selected_index(&AppView::ItemListView))
     }
 
-    fn current_item_detail(&self) -> &op::ItemDetailsField {
-        let i = self.selected_index(&AppView::ItemView);
-        &self.item_details.as_ref().unwrap().fields[i]
+    /// Rows of the item view. Fields that have a label come first if they aren't
+    /// in a labelled section, the ones that matter most for the category leading
+    /// and followed by websites. Then a heading for each section followed by its
+    /// fields unless it's collapsed, then attachments.
+    fn item_rows(&self) -> Vec<ItemRow<'_>> {
+        let item_details = match &self.item_details {
+            Some(item_details) => item_details,
+            None => return Vec::new(),
+        };
+        let fields: Vec<&op::ItemDetailsField> = item_details.fields
+            .iter()
+            .filter(|field| field.value.is_some() && field.label.is_some())
+            .collect();
+        let leading = item_details.category.leading_fields();
+        let leading_i = |field: &op::ItemDetailsField| {
+            leading.iter().position(|id| field.id.as_deref() == Some(id))
+        };
+        let mut top: Vec<&op::ItemDetailsField> = fields
+            .iter()
+            .filter(|field| item_details.section_of(field).is_none())
+            .copied()
+            .collect();
+        // Stable so fields that aren't leading keep their order
+        top.sort_by_key(|field| leading_i(field).unwrap_or(leading.len()));
+        let n_leading = top.iter().filter(|field| leading_i(field).is_some()).count();
+
+        let mut rows: Vec<ItemRow> = Vec::new();
+        if ui::item_summary(item_details).is_some() {
+            rows.push(ItemRow::Summary);
+        }
+        rows.extend(top[..n_leading].iter().map(|field| ItemRow::Field(field)));
+        rows.extend(item_details.urls.iter().flatten().map(ItemRow::Url));
+        rows.extend(top[n_leading..].iter().map(|field| ItemRow::Field(field)));
+        for section in item_details.sections.iter().filter(|section| section.heading().is_some()) {
+            let section_fields: Vec<&op::ItemDetailsField> = fields
+                .iter()
+                .filter(|field| item_details.section_of(field).map(|s| &s.id) == Some(&section.id))
+                .copied()
+                .collect();
+            let collapsed = self.collapsed_sections.contains(&section.id);
+            rows.push(ItemRow::Section { section, collapsed, len: section_fields.len() });
+            if !collapsed {
+                rows.extend(section_fields.into_iter().map(ItemRow::Field));
+            }
+        }
+        rows.extend(item_details.files.iter().map(ItemRow::File));
+        rows
+    }
+
+    fn current_item_row(&self) -> Option<ItemRow<'_>> {
+        self.item_rows().get(self.selected_index(&AppView::ItemView)).copied()
+    }
+
+    fn current_item_detail(&self) -> Option<&op::ItemDetailsField> {
+        match self.current_item_row() {
+            Some(ItemRow::Field(field)) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Hide or show the fields of the highlighted section
+    fn toggle_section(&mut self) {
+        if let Some(ItemRow::Section { section, .. }) = self.current_item_row() {
+            let id = section.id.clone();
+            if !self.collapsed_sections.remove(&id) {
+                self.collapsed_sections.insert(id);
+            }
+        }
+    }
+
+    /// Highlighted attachment, or the document itself when a document is open
+    fn current_item_file(&self) -> Option<&op::ItemFile> {
+        match self.current_item_row() {
+            Some(ItemRow::File(file)) => Some(file),
+            _ => self.item_details
+                .as_ref()
+                .filter(|item_details| item_details.category == op::Category::Document)
+                .and_then(|item_details| item_details.files.first()),
+        }
     }
 
     fn reset_cmd_input(&mut self) {
-        self.input_mode = InputMode::Normal;
+        // Commands like `:delete` may have already moved on to another mode
+        if self.input_mode == InputMode::Command {
+            self.input_mode = InputMode::Normal;
+        }
         self.cmd_input = String::from("");
     }
 
@@ -212,11 +805,11 @@ impl App {
     }
 
     fn run_command(&mut self) {
-        let components: Vec<&str> = self.cmd_input.split(" ").collect();
+        let components: Vec<&str> = self.cmd_input.split(' ').collect();
         let n_args = components.len();
         // From https://github.com/rust-lang/rust/issues/59159 immutable references
         // have to all be finished with by the time the mutable reference is used
-        let arg0 = String::from(*components.get(0).unwrap_or(&""));
+        let arg0 = String::from(*components.first().unwrap_or(&""));
         let arg1 = String::from(*components.get(1).unwrap_or(&""));
         let arg2 = String::from(*components.get(2).unwrap_or(&""));
 
@@ -226,10 +819,30 @@ impl App {
         match ch {
             ':' => match cmd {
                 "e" => self.populate_items(),
+                "new" => self.populate_item_template(components[1..].join(" ")),
+                "w" => self.submit_item_template(),
+                "account" => self.switch_account(arg1),
+                "vault" => if n_args == 1 {
+                    self.populate_vaults()
+                } else {
+                    self.switch_vault(components[1..].join(" "))
+                },
+                "generate" => self.open_generator(),
+                "save" => self.save_file(components[1..].join(" ")),
+                "inject" => self.inject_template(components[1..].join(" ")),
+                "run" => self.run_with_item_env(components[1..].join(" ")),
+                "export" => self.export_items(components[1..].join(" ")),
+                "tag" => self.switch_tag(components[1..].join(" ")),
+                "category" => self.switch_category(components[1..].join(" ")),
+                "filter" => self.switch_date_filters(&components[1..].join(" ")),
+                "delete" => self.confirm_remove_item(false),
+                "archive" => self.confirm_remove_item(true),
+                "favorites" => self.toggle_favorites_first(),
                 "q" => self.is_running = false,
                 "qa" => self.is_running = false,
                 "sort" => {
                     self.item_list_sort_config = SortConfig {
+                        favorites_first: self.item_list_sort_config.favorites_first,
                         header: arg1,
                         sort_direction: {
                             if n_args == 2 {
@@ -254,40 +867,635 @@ impl App {
         };
     }
 
-    fn yank(&self) {
-        let s = match self.app_view {
-            AppView::ItemListView => self.current_item().title.as_str(),
-            AppView::ItemView => {
-                self.current_item_detail().value.as_ref().unwrap().as_str()
+    /// Copy the highlighted title, field value or vault name, saying what was
+    /// copied on the message line
+    fn yank(&mut self) {
+        let (what, s) = match self.app_view {
+            AppView::ItemListView => match self.current_item() {
+                Some(item) => (String::from("title"), item.title.clone()),
+                None => return,
+            },
+            AppView::ItemView => match self.current_item_row() {
+                Some(ItemRow::Field(field)) => {
+                    let label = field.label.clone().unwrap_or_else(|| String::from("field"));
+                    // The code is what's wanted, not the secret it comes from
+                    match (field.totp(), &field.value) {
+                        (Some(totp), _) => (format!("{} code", label), totp.now().0),
+                        (None, Some(value)) => (label, value.clone()),
+                        (None, None) => {
+                            self.message = Some(Message::Error(format!("{} is empty", label)));
+                            return;
+                        },
+                    }
+                },
+                Some(ItemRow::Url(url)) => (String::from("website"), url.href.clone()),
+                // Contents can be binary, `s` saves them to a file instead
+                Some(ItemRow::File(file)) => (String::from("file name"), file.name.clone()),
+                Some(ItemRow::Summary) | Some(ItemRow::Section { .. }) | None => return,
+            },
+            AppView::VaultListView => match self.vaults.get(self.selected_index(&AppView::VaultListView)) {
+                Some(vault) => (String::from("vault name"), vault.name.clone()),
+                None => return,
+            },
+            AppView::ItemCreateView => return,
+        };
+        self.copy_and_report(&what, &s);
+    }
+
+    /// Copy an `op://` secret reference to the highlighted field or attachment
+    /// instead of its value, for config files that shouldn't hold the plaintext
+    fn yank_reference(&mut self) {
+        let reference = match (&self.item_details, self.current_item_row()) {
+            (Some(item), Some(ItemRow::Field(field))) => item.secret_reference(field),
+            (Some(item), Some(ItemRow::File(file))) => item.file_reference(file),
+            (_, Some(ItemRow::Url(_))) => {
+                self.message = Some(Message::Error(String::from("websites don't have a secret reference")));
+                return;
             },
+            _ => return,
         };
-        let cmd_components: Vec<&str> = self.clipboard_bin.as_str().split(" ").collect();
+        self.copy_and_report(&reference, &reference);
+    }
+
+    /// Copy `s` and say what was copied on the message line
+    fn copy_and_report(&mut self, what: &str, s: &str) {
+        self.message = Some(match self.copy_to_clipboard(s) {
+            Ok(_) => Message::Info(format!("copied {}", what)),
+            Err(why) => {
+                tracing::error!("Couldn't copy with {}: {}", self.clipboard_bin, why);
+                Message::Error(format!("couldn't copy with `{}`: {}", self.clipboard_bin, why))
+            },
+        });
+    }
+
+    /// Pipe `s` into `clipboard_bin`
+    fn copy_to_clipboard(&self, s: &str) -> io::Result<()> {
+        let cmd_components: Vec<&str> = self.clipboard_bin.as_str().split(' ').collect();
         let mut cmd = Command::new(cmd_components[0]);
         if cmd_components.len() > 1 {
             cmd.args(&cmd_components[1..]);
         }
-        let process = match cmd
+        // Nothing reads stdout, a pipe would kill anything that echoes its input
+        let mut process = cmd
             .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn() {
-            Err(why) => {
-                tracing::error!("Couldn't spawn {:?}: {}", cmd_components, why);
-                panic!("Couldn't spawn {}: {}", self.clipboard_bin, why);
-            },
-            Ok(process) => process,
+            .stdout(Stdio::null())
+            .spawn()?;
+        // Piped above so it's always there
+        process.stdin.take().unwrap().write_all(s.as_bytes())
+    }
+
+    /// Fetch details of the selected item in the background, the item view is
+    /// opened once they come back
+    fn populate_item_details(&mut self) {
+        let id = match self.app_view {
+            AppView::ItemListView => self.current_item().map(|item| item.id.clone()),
+            AppView::ItemView => self.item_details.as_ref().map(|item| item.id.clone()),
+            AppView::ItemCreateView | AppView::VaultListView => None,
         };
-        match process.stdin.unwrap().write_all(s.as_bytes()) {
-            Err(why) => panic!("Couldn't write to {} stdin: {}", self.clipboard_bin, why),
-            Ok(_) => {},
+        if let Some(id) = id {
+            self.pending_item_id = Some(id.clone());
+            self.worker.spawn(move |session| {
+                let item_details = session.get_item(&id, None);
+                Response::ItemDetails(id.clone(), item_details)
+            });
         }
     }
 
-    fn populate_item_details(&mut self) {
-        // FIXME: Set to None if unwrap fails and amend things that grab `item_details`
-        // need to handle it too
-        let mut item_details = self.session.get_item(&self.current_item().id).unwrap();
+    fn set_item_details(&mut self, item_details: Result<op::ItemDetails, OpError>) {
+        let mut item_details = match item_details {
+            Ok(item_details) => item_details,
+            Err(err) => return self.report_error("couldn't get item", &err),
+        };
         item_details.fill_none_fields();
+        if self.item_details.as_ref().map(|item| &item.id) != Some(&item_details.id) {
+            self.item_table_state.select(Some(0));
+            self.collapsed_sections.clear();
+        }
         self.item_details = Some(item_details);
+        self.app_view = AppView::ItemView;
+    }
+
+    /// Fetch a blank item for `category` in the background, the create view is
+    /// opened once it comes back
+    fn populate_item_template(&mut self, category: String) {
+        if category.is_empty() {
+            return;
+        }
+        self.worker.spawn(move |session| Response::ItemTemplate(session.get_item_template(&category)));
+    }
+
+    fn set_item_template(&mut self, item_template: Result<op::ItemTemplate, OpError>) {
+        match item_template {
+            Ok(item_template) => {
+                self.item_template = Some(item_template);
+                self.item_create_table_state.select(Some(0));
+                self.app_view = AppView::ItemCreateView;
+            },
+            Err(err) => self.report_error("couldn't get item template", &err),
+        }
+    }
+
+    /// Create an item out of the filled in template with `op item create`, in the
+    /// vault the list is scoped to if there is one
+    fn submit_item_template(&mut self) {
+        if self.app_view != AppView::ItemCreateView {
+            return;
+        }
+        if let Some(item_template) = self.item_template.clone() {
+            // Items go to the vault being listed so they show up in it
+            let vault = self.vault.clone();
+            self.worker.spawn(move |session| {
+                Response::ItemCreated(session.create_item(&item_template, vault.as_deref()))
+            });
+        }
+    }
+
+    /// Add a newly created item to the list and select it, without reloading everything.
+    /// Items the list is filtered out of are only reported.
+    fn insert_created_item(&mut self, item_details: Result<op::ItemDetails, OpError>) {
+        match item_details {
+            Ok(item_details) => {
+                let entry = op::ItemListEntry::from(&item_details);
+                if self.is_listed(&entry) {
+                    let id = item_details.id.clone();
+                    self.items.push(entry);
+                    self.sort_item_list();
+                    if self.search_state.is_some() {
+                        self.search_item_list();
+                    }
+                    if let Some(i) = self.items.iter().position(|item| item.id == id) {
+                        self.item_list_table_state.select(Some(i));
+                    }
+                    self.message = Some(Message::Info(format!("created \"{}\"", item_details.title)));
+                } else {
+                    self.message = Some(Message::Info(format!("created \"{}\", hidden by filter", item_details.title)));
+                }
+                self.item_template = None;
+                self.app_view = AppView::ItemListView;
+            },
+            Err(err) => self.report_error("couldn't create item", &err),
+        }
+    }
+
+    /// Only list items from `account`, or from every account if it's empty or "all"
+    fn switch_account(&mut self, account: String) {
+        let account = match account.as_str() {
+            "" | "all" => None,
+            _ => Some(account),
+        };
+        self.worker.spawn(move |session| {
+            let res = session.set_account(account.as_deref()).map(|_| account.clone());
+            Response::Account(res)
+        });
+    }
+
+    /// Table title showing which account items are listed from when there's a
+    /// choice, and which vault if one was picked
+    fn item_list_title(&self) -> String {
+        let mut title = String::from("Table");
+        if self.accounts.len() > 1 {
+            title.push_str(&format!(" [{}]", self.account.as_deref().unwrap_or("all accounts")));
+        }
+        if let Some(vault) = &self.vault {
+            title.push_str(&format!(" [vault: {}]", vault));
+        }
+        if let Some(tag) = &self.tag {
+            title.push_str(&format!(" [tag: {}]", tag));
+        }
+        if let Some(category) = &self.category {
+            title.push_str(&format!(" [category: {}]", category));
+        }
+        if !self.date_filters.is_empty() {
+            let filters: Vec<String> = self.date_filters.iter().map(|filter| filter.to_string()).collect();
+            title.push_str(&format!(" [filter: {}]", filters.join(" ")));
+        }
+        title
+    }
+
+    /// Open a y/n popup for deleting or archiving the open item in the item view or
+    /// the selected item in the list view
+    fn confirm_remove_item(&mut self, archive: bool) {
+        let item = match self.app_view {
+            AppView::ItemListView => self.current_item().map(|item| (item.id.clone(), item.title.clone())),
+            AppView::ItemView => self.item_details.as_ref().map(|item| (item.id.clone(), item.title.clone())),
+            AppView::ItemCreateView | AppView::VaultListView => None,
+        };
+        if let Some((id, title)) = item {
+            self.confirmation = Some(if archive {
+                Confirmation::Archive { id, title }
+            } else {
+                Confirmation::Delete { id, title }
+            });
+            self.input_mode = InputMode::Confirm;
+        }
+    }
+
+    /// Run whatever is waiting on the y/n popup if `confirmed`, otherwise drop it
+    fn answer_confirmation(&mut self, confirmed: bool) {
+        self.input_mode = InputMode::Normal;
+        let confirmation = match self.confirmation.take() {
+            Some(confirmation) if confirmed => confirmation,
+            _ => return,
+        };
+        match confirmation {
+            Confirmation::Delete { id, .. } => self.worker.spawn(move |session| {
+                let res = session.delete_item(&id);
+                Response::ItemRemoved(id.clone(), res)
+            }),
+            Confirmation::Archive { id, .. } => self.worker.spawn(move |session| {
+                let res = session.archive_item(&id);
+                Response::ItemRemoved(id.clone(), res)
+            }),
+            Confirmation::Export { path, format, .. } => {
+                let items = self.items.clone();
+                let headers = self.headers.clone();
+                self.worker.spawn(move |session| {
+                    let res = items
+                        .iter()
+                        .map(|item| session.get_item(&item.id, None))
+                        .collect::<Result<Vec<op::ItemDetails>, OpError>>()
+                        .and_then(|details| Ok(export::render(&items, &headers, Some(&details), format)?))
+                        .and_then(|out| Ok(util::write_private_file(&path, &out)?))
+                        .map(|_| items.len());
+                    Response::Exported(path.clone(), res)
+                });
+            },
+        }
+    }
+
+    /// Drop an item from the list, shifting the selection and search matches
+    /// below it up a row so they still point at the same items
+    fn remove_item(&mut self, id: &str) {
+        if self.item_details.as_ref().map(|item| item.id.as_str()) == Some(id) {
+            self.item_details = None;
+            self.pending_item_id = None;
+            self.app_view = AppView::ItemListView;
+        }
+        let removed_i = match self.items.iter().position(|item| item.id == id) {
+            Some(i) => i,
+            None => return,
+        };
+        self.items.remove(removed_i);
+
+        if let Some(ref mut search_state) = self.search_state {
+            if let Some(removed_mi) = search_state.match_idxs.iter().position(|i| *i == removed_i) {
+                search_state.match_idxs.remove(removed_mi);
+                // Step back one so `n` lands on whatever moved into the removed match's place
+                search_state.selected_match_idx = match search_state.selected_match_idx {
+                    Some(mi) if mi >= removed_mi => mi.checked_sub(1),
+                    selected_mi => selected_mi,
+                };
+            }
+            for i in search_state.match_idxs.iter_mut() {
+                if *i > removed_i {
+                    *i -= 1;
+                }
+            }
+            if search_state.match_idxs.is_empty() {
+                search_state.selected_match_idx = None;
+            }
+        }
+
+        let selected_i = self.selected_index(&AppView::ItemListView);
+        if selected_i > removed_i || selected_i > self.table_max_index(&AppView::ItemListView) {
+            self.item_list_table_state.select(Some(selected_i.saturating_sub(1)));
+        }
+    }
+
+    /// Start editing the selected field, prefilled with its current value
+    fn enter_edit_mode(&mut self) {
+        let value = match self.app_view {
+            AppView::ItemView => match self.current_item_detail() {
+                Some(field) => field.value.clone(),
+                None => return,
+            },
+            AppView::ItemCreateView => {
+                let item_template = match &self.item_template {
+                    Some(item_template) => item_template,
+                    None => return,
+                };
+                match self.selected_index(&AppView::ItemCreateView) {
+                    0 => Some(item_template.title.clone()),
+                    i => item_template.fields[i - 1].value.clone(),
+                }
+            },
+            AppView::ItemListView | AppView::VaultListView => return,
+        };
+        self.edit_input = value.unwrap_or_default();
+        self.input_mode = InputMode::Edit;
+    }
+
+    fn reset_edit_input(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.edit_input = String::from("");
+    }
+
+    /// Write the edited value back with `op item edit`, the item view is refreshed
+    /// from the returned item. New items just keep the value until they're submitted.
+    fn save_edit(&mut self) {
+        if self.app_view == AppView::ItemCreateView {
+            let i = self.selected_index(&AppView::ItemCreateView);
+            if let Some(ref mut item_template) = self.item_template {
+                let value = std::mem::take(&mut self.edit_input);
+                match i {
+                    0 => item_template.title = value,
+                    i => item_template.fields[i - 1].value = Some(value),
+                }
+            }
+            self.reset_edit_input();
+            return;
+        }
+        let assignment = match (&self.item_details, self.current_item_detail()) {
+            (Some(item_details), Some(field)) if field.value.as_ref() != Some(&self.edit_input) => {
+                Some(op::FieldAssignment::for_field(item_details, field, &self.edit_input))
+            },
+            _ => None,
+        };
+        if let Some(assignment) = assignment {
+            self.edit_open_item(assignment);
+        }
+        self.reset_edit_input();
+    }
+
+    /// Set a field on the item open in the item view with `op item edit`, the view
+    /// is refreshed from the returned item
+    fn edit_open_item(&mut self, assignment: op::FieldAssignment) {
+        let id = match &self.item_details {
+            Some(item_details) => item_details.id.clone(),
+            None => return,
+        };
+        self.pending_item_id = Some(id.clone());
+        self.worker.spawn(move |session| {
+            let item_details = session.edit_item(&id, std::slice::from_ref(&assignment));
+            Response::ItemDetails(id.clone(), item_details)
+        });
+    }
+
+    /// Write the highlighted attachment, or the open document, to `path` with
+    /// 0600 permissions. A directory or no path at all keeps the file's own name.
+    fn save_file(&mut self, path: String) {
+        let (item_details, file) = match (&self.item_details, self.current_item_file()) {
+            (Some(item_details), Some(file)) if self.app_view == AppView::ItemView => (item_details.clone(), file.clone()),
+            _ => {
+                self.message = Some(Message::Error(String::from("highlight an attachment to save it")));
+                return;
+            },
+        };
+        let mut path = util::expand_home(path.trim());
+        if path.is_empty() || Path::new(&path).is_dir() {
+            path = Path::new(&path).join(&file.name).to_string_lossy().into_owned();
+        }
+        self.worker.spawn(move |session| {
+            let res = session
+                .get_file(&item_details, &file)
+                .and_then(|contents| Ok(util::write_private_file(&path, &contents)?));
+            Response::FileSaved(path.clone(), res)
+        });
+    }
+
+    /// Fill in the `{{ op://... }}` references in `template` and write the result
+    /// to `output` with 0600 permissions, nothing is written unless they all
+    /// resolve. The open item is used as is instead of being fetched again. The
+    /// two paths in `args` can be quoted when they have spaces in them.
+    fn inject_template(&mut self, args: String) {
+        let (template, output) = match util::split_args(&args).as_deref() {
+            Some([template, output]) => (util::expand_home(template), util::expand_home(output)),
+            _ => {
+                self.message = Some(Message::Error(String::from("usage: :inject <template> <output>, quote paths with spaces")));
+                return;
+            },
+        };
+        let cached: Vec<op::ItemDetails> = self.item_details.iter().cloned().collect();
+        self.worker.spawn(move |session| {
+            let res = fs::read_to_string(&template)
+                .map_err(InjectError::from)
+                .and_then(|text: String| inject::inject(session, &text, cached.clone()))
+                .and_then(|injected| {
+                    util::write_private_file(&output, injected.text.as_bytes())?;
+                    Ok(injected.references)
+                });
+            Response::Injected(output.clone(), res)
+        });
+    }
+
+    /// Queue `command` to run with the open item's fields as environment variables,
+    /// the draw loop runs it once it has left the alternate screen
+    fn run_with_item_env(&mut self, command: String) {
+        let item = match (&self.item_details, &self.app_view) {
+            (Some(item), AppView::ItemView) => item,
+            _ => {
+                self.message = Some(Message::Error(String::from("open an item to run a command with its fields")));
+                return;
+            },
+        };
+        let command = String::from(command.trim());
+        if command.is_empty() {
+            self.message = Some(Message::Error(String::from("usage: :run <command>")));
+            return;
+        }
+        let env = run::item_env(item, &self.run_env);
+        let names: Vec<&str> = env.iter().map(|(name, _)| name.as_str()).collect();
+        tracing::info!("Running `{}` with {} from \"{}\"", command, names.join(", "), item.title);
+        self.pending_run = Some(PendingRun { command, env });
+    }
+
+    /// Report how a `:run` command went once the interface is back
+    pub fn finish_run(&mut self, command: &str, status: io::Result<ExitStatus>) {
+        self.message = Some(match status {
+            Ok(status) if status.success() => Message::Info(format!("`{}` finished", command)),
+            Ok(status) => Message::Error(format!("`{}` failed: {}", command, status)),
+            Err(err) => {
+                tracing::error!("Couldn't run {}: {}", command, err);
+                Message::Error(format!("couldn't run `{}`: {}", command, err))
+            },
+        });
+    }
+
+    /// Write the listed items, as filtered and sorted, to a file with a key or
+    /// column per header. `--with-secrets` fetches every item and adds its field
+    /// values after a y/n confirmation.
+    fn export_items(&mut self, args: String) {
+        let usage = "usage: :export <path> [json|csv|yaml] [--with-secrets]";
+        let mut path = None;
+        let mut format = None;
+        let mut with_secrets = false;
+        for arg in args.split_whitespace() {
+            match arg {
+                "--with-secrets" => with_secrets = true,
+                _ if path.is_none() => path = Some(util::expand_home(arg)),
+                _ if format.is_none() => match export::Format::from_name(arg) {
+                    Some(f) => format = Some(f),
+                    None => {
+                        self.message = Some(Message::Error(format!("\"{}\" isn't a format, {}", arg, usage)));
+                        return;
+                    },
+                },
+                _ => {
+                    self.message = Some(Message::Error(String::from(usage)));
+                    return;
+                },
+            }
+        }
+        let path = match path {
+            Some(path) => path,
+            None => {
+                self.message = Some(Message::Error(String::from(usage)));
+                return;
+            },
+        };
+        let format = format.unwrap_or_else(|| export::Format::from_path(&path));
+        if with_secrets {
+            self.confirmation = Some(Confirmation::Export { path, format, count: self.items.len() });
+            self.input_mode = InputMode::Confirm;
+            return;
+        }
+        let res = export::render(&self.items, &self.headers, None, format)
+            .and_then(|out| util::write_private_file(&path, &out));
+        self.message = Some(match res {
+            Ok(_) => Message::Info(format!("exported {} item(s) to {}", self.items.len(), path)),
+            Err(err) => Message::Error(format!("couldn't export to {}: {}", path, err)),
+        });
+    }
+
+    /// Prefill `:save` with the name of the file that would be saved
+    fn enter_save_command(&mut self) {
+        match self.current_item_file() {
+            Some(file) => {
+                let cmd_input = format!(":save {}", file.name);
+                self.enter_command_mode(&cmd_input);
+            },
+            None => self.message = Some(Message::Error(String::from("highlight an attachment to save it"))),
+        }
+    }
+
+    /// Open the password generator popup with a fresh preview. The word file is
+    /// only read the first time, passphrases are unavailable without one.
+    fn open_generator(&mut self) {
+        if self.generator.is_none() {
+            let words = generator::read_word_file(&self.word_file).unwrap_or_else(|err| {
+                tracing::info!("No word list at {}: {}", self.word_file, err);
+                Vec::new()
+            });
+            self.generator = Some(Generator::new(words));
+        }
+        self.input_mode = InputMode::Generate;
+        self.regenerate();
+    }
+
+    fn regenerate(&mut self) {
+        if let Some(generator) = &self.generator {
+            self.generated = generator.generate();
+        }
+    }
+
+    /// Change a generator setting and show a new preview with it
+    fn update_generator(&mut self, f: impl FnOnce(&mut Generator)) {
+        if let Some(generator) = self.generator.as_mut() {
+            f(generator);
+            if generator.recipe == Recipe::Words && generator.words.is_empty() {
+                generator.recipe = Recipe::Characters;
+                self.message = Some(Message::Error(format!("no words in {}", self.word_file)));
+            }
+        }
+        self.regenerate();
+    }
+
+    fn close_generator(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.generated = String::from("");
+    }
+
+    /// Copy the generated value and close the popup
+    fn yank_generated(&mut self) {
+        let generated = std::mem::take(&mut self.generated);
+        self.message = Some(match self.copy_to_clipboard(&generated) {
+            Ok(_) => Message::Info(String::from("copied generated password")),
+            Err(why) => Message::Error(format!("couldn't copy with `{}`: {}", self.clipboard_bin, why)),
+        });
+        self.close_generator();
+    }
+
+    /// Put the generated value in the highlighted field if it's concealed, otherwise
+    /// in the item's password field. Saved with `op item edit` in the item view,
+    /// kept until `:w` in the create view.
+    fn apply_generated(&mut self) {
+        let generated = std::mem::take(&mut self.generated);
+        self.close_generator();
+        if generated.is_empty() {
+            return;
+        }
+        let is_target = |field: &op::ItemDetailsField| field.is_concealed() || field.purpose.as_deref() == Some("PASSWORD");
+        match self.app_view {
+            AppView::ItemView if self.item_details.is_some() => {
+                let item_details = self.item_details.as_ref().unwrap();
+                let field = match self.current_item_detail() {
+                    Some(selected) if selected.is_concealed() => Some(selected),
+                    _ => item_details.fields.iter().find(|field| is_target(field)),
+                };
+                match field.filter(|field| field.label.is_some()) {
+                    Some(field) => {
+                        let assignment = op::FieldAssignment::for_field(item_details, field, &generated);
+                        self.message = Some(Message::Info(format!("set {}", assignment.label)));
+                        self.edit_open_item(assignment);
+                    },
+                    None => self.message = Some(Message::Error(String::from("no password field to set"))),
+                }
+            },
+            AppView::ItemCreateView => {
+                let selected_i = self.selected_index(&AppView::ItemCreateView).checked_sub(1);
+                let fields = match self.item_template.as_mut() {
+                    Some(item_template) => &mut item_template.fields,
+                    None => return,
+                };
+                let field_i = selected_i
+                    .filter(|i| fields[*i].is_concealed())
+                    .or_else(|| fields.iter().position(is_target));
+                match field_i {
+                    Some(i) => {
+                        fields[i].value = Some(generated);
+                        self.message = Some(Message::Info(String::from("set password, `:w` to create the item")));
+                    },
+                    None => self.message = Some(Message::Error(String::from("no password field to set"))),
+                }
+            },
+            _ => self.message = Some(Message::Error(String::from("open an item to set its password"))),
+        }
+    }
+
+    /// Open the password popup for `account`, any calls that failed on its
+    /// expired session stay queued until it's answered. Whatever was open before,
+    /// like an edit or a confirmation, is picked back up when the popup closes.
+    fn open_signin(&mut self, account: String, error: Option<String>) {
+        if self.input_mode == InputMode::Password {
+            return;
+        }
+        self.signin_account = Some(account);
+        self.signin_error = error;
+        self.password_input = String::from("");
+        let mode = std::mem::replace(&mut self.input_mode, InputMode::Password);
+        self.mode_before_signin = Some(mode).filter(|mode| *mode != InputMode::Normal);
+    }
+
+    /// Sign in with the typed password in the background, queued calls are run
+    /// again once it succeeds
+    fn submit_signin(&mut self) {
+        self.input_mode = self.mode_before_signin.take().unwrap_or(InputMode::Normal);
+        let password = std::mem::take(&mut self.password_input);
+        if let Some(account) = self.signin_account.take() {
+            self.worker.spawn(move |session| {
+                let res = session.signin(&account, &password);
+                Response::Signin(account.clone(), res)
+            });
+        }
+    }
+
+    /// Close the password popup and give up on everything waiting on it
+    fn cancel_signin(&mut self) {
+        self.input_mode = self.mode_before_signin.take().unwrap_or(InputMode::Normal);
+        self.password_input = String::from("");
+        self.signin_account = None;
+        self.signin_error = None;
+        self.retry_jobs.clear();
     }
 
     fn search_item_list(&mut self) {
@@ -325,20 +1533,15 @@ impl App {
 
     /// Currently only handles KeyEvents, modifies app state based on inputs
     pub fn handle_event(&mut self, event: Event) {
-        match event {
-            Event::Key(key_event) => match self.input_mode {
+        if let Event::Key(key_event) = event {
+            self.message = None;
+            match self.input_mode {
                 InputMode::Normal => match self.app_view {
                     AppView::ItemListView => match key_event.code {
-                        KeyCode::Char('d') => match key_event.modifiers {
-                            // FIXME: This should dynamically go halfway
-                            KeyModifiers::CONTROL => self.add_selected_index(6, &AppView::ItemListView),
-                            _ => {}
-                        }
-                        KeyCode::Char('u') => match key_event.modifiers {
-                            // FIXME: This should dynamically go halfway
-                            KeyModifiers::CONTROL => self.add_selected_index(-6, &AppView::ItemListView),
-                            _ => {}
-                        }
+                        // FIXME: This should dynamically go halfway
+                        KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => self.add_selected_index(6, &AppView::ItemListView),
+                        // FIXME: This should dynamically go halfway
+                        KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => self.add_selected_index(-6, &AppView::ItemListView),
                         KeyCode::Char('q') => self.is_running = false,
                         KeyCode::Down      => self.add_selected_index(1, &AppView::ItemListView),
                         KeyCode::Char('j') => self.add_selected_index(1, &AppView::ItemListView),
@@ -351,31 +1554,72 @@ impl App {
                         KeyCode::Char(':') => self.enter_command_mode(":"),
                         KeyCode::Char('/') => self.enter_command_mode("/"),
                         KeyCode::Char('y') => self.yank(),
-                        KeyCode::Enter     => {
-                            self.populate_item_details();
-                            self.app_view = AppView::ItemView;
-                        },
+                        KeyCode::Enter     => self.populate_item_details(),
                         KeyCode::Char('R') => self.populate_items(),
+                        KeyCode::Char('D') => self.confirm_remove_item(false),
+                        KeyCode::Char('A') => self.confirm_remove_item(true),
+                        KeyCode::Char('V') => self.populate_vaults(),
+                        KeyCode::Char('f') => self.toggle_favorite(),
                         _ => {}
                     },
                     AppView::ItemView => match key_event.code {
-                        KeyCode::Char('d') => match key_event.modifiers {
-                            // FIXME: This should dynamically go halfway
-                            KeyModifiers::CONTROL => self.add_selected_index(6, &AppView::ItemView),
-                            _ => {}
-                        }
-                        KeyCode::Char('u') => match key_event.modifiers {
-                            // FIXME: This should dynamically go halfway
-                            KeyModifiers::CONTROL => self.add_selected_index(-6, &AppView::ItemView),
-                            _ => {}
-                        }
-                        KeyCode::Char('q') => self.app_view = AppView::ItemListView,
+                        // FIXME: This should dynamically go halfway
+                        KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => self.add_selected_index(6, &AppView::ItemView),
+                        // FIXME: This should dynamically go halfway
+                        KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => self.add_selected_index(-6, &AppView::ItemView),
+                        KeyCode::Char('q') => {
+                            // Drop any reload still in flight so it doesn't reopen this view
+                            self.pending_item_id = None;
+                            self.app_view = AppView::ItemListView;
+                        },
                         KeyCode::Char('R') => self.populate_item_details(),
                         KeyCode::Down      => self.add_selected_index(1, &AppView::ItemView),
                         KeyCode::Char('j') => self.add_selected_index(1, &AppView::ItemView),
                         KeyCode::Up        => self.add_selected_index(-1, &AppView::ItemView),
                         KeyCode::Char('k') => self.add_selected_index(-1, &AppView::ItemView),
                         KeyCode::Char('y') => self.yank(),
+                        KeyCode::Char('Y') => self.yank_reference(),
+                        KeyCode::Char('i') => self.enter_edit_mode(),
+                        KeyCode::Char('s') => self.enter_save_command(),
+                        KeyCode::Char('f') => self.toggle_favorite(),
+                        KeyCode::Enter     => self.toggle_section(),
+                        KeyCode::Char(':') => self.enter_command_mode(":"),
+                        _ => {}
+                    },
+                    AppView::ItemCreateView => match key_event.code {
+                        // FIXME: This should dynamically go halfway
+                        KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => self.add_selected_index(6, &AppView::ItemCreateView),
+                        // FIXME: This should dynamically go halfway
+                        KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => self.add_selected_index(-6, &AppView::ItemCreateView),
+                        KeyCode::Char('q') => {
+                            self.item_template = None;
+                            self.app_view = AppView::ItemListView;
+                        },
+                        KeyCode::Down      => self.add_selected_index(1, &AppView::ItemCreateView),
+                        KeyCode::Char('j') => self.add_selected_index(1, &AppView::ItemCreateView),
+                        KeyCode::Up        => self.add_selected_index(-1, &AppView::ItemCreateView),
+                        KeyCode::Char('k') => self.add_selected_index(-1, &AppView::ItemCreateView),
+                        KeyCode::Char(':') => self.enter_command_mode(":"),
+                        KeyCode::Char('i') => self.enter_edit_mode(),
+                        _ => {}
+                    },
+                    AppView::VaultListView => match key_event.code {
+                        KeyCode::Char('q') => self.app_view = AppView::ItemListView,
+                        KeyCode::Down      => self.add_selected_index(1, &AppView::VaultListView),
+                        KeyCode::Char('j') => self.add_selected_index(1, &AppView::VaultListView),
+                        KeyCode::Up        => self.add_selected_index(-1, &AppView::VaultListView),
+                        KeyCode::Char('k') => self.add_selected_index(-1, &AppView::VaultListView),
+                        KeyCode::Char('y') => self.yank(),
+                        KeyCode::Char('a') => {
+                            self.switch_vault(String::from("all"));
+                            self.app_view = AppView::ItemListView;
+                        },
+                        KeyCode::Enter     => {
+                            if let Some(vault) = self.vaults.get(self.selected_index(&AppView::VaultListView)) {
+                                self.switch_vault(vault.name.clone());
+                                self.app_view = AppView::ItemListView;
+                            }
+                        },
                         _ => {}
                     },
                 },
@@ -404,59 +1648,261 @@ impl App {
                     KeyCode::Esc => self.reset_cmd_input(),
                     _ => {},
                 }
-            },
-            _ => {}
+                InputMode::Confirm => match key_event.code {
+                    KeyCode::Char('y') => self.answer_confirmation(true),
+                    KeyCode::Char('n') => self.answer_confirmation(false),
+                    KeyCode::Esc       => self.answer_confirmation(false),
+                    _ => {},
+                },
+                InputMode::Edit => match key_event.code {
+                    KeyCode::Enter     => self.save_edit(),
+                    KeyCode::Char(c)   => self.edit_input.push(c),
+                    KeyCode::Backspace => { self.edit_input.pop(); },
+                    KeyCode::Esc       => self.reset_edit_input(),
+                    _ => {},
+                },
+                InputMode::Generate => match key_event.code {
+                    KeyCode::Char('w')                   => self.update_generator(|g| g.recipe = match g.recipe {
+                        Recipe::Characters => Recipe::Words,
+                        Recipe::Words => Recipe::Characters,
+                    }),
+                    KeyCode::Char('+') | KeyCode::Right  => self.update_generator(|g| g.add_length(1)),
+                    KeyCode::Char('-') | KeyCode::Left   => self.update_generator(|g| g.add_length(-1)),
+                    KeyCode::Char('a')                   => self.update_generator(|g| g.lowercase = !g.lowercase),
+                    KeyCode::Char('A')                   => self.update_generator(|g| g.uppercase = !g.uppercase),
+                    KeyCode::Char('d')                   => self.update_generator(|g| g.digits = !g.digits),
+                    KeyCode::Char('s')                   => self.update_generator(|g| g.symbols = !g.symbols),
+                    KeyCode::Char('x')                   => self.update_generator(|g| g.exclude_ambiguous = !g.exclude_ambiguous),
+                    KeyCode::Char('r')                   => self.regenerate(),
+                    KeyCode::Char('y')                   => self.yank_generated(),
+                    KeyCode::Enter                       => self.apply_generated(),
+                    KeyCode::Esc | KeyCode::Char('q')    => self.close_generator(),
+                    _ => {},
+                },
+                InputMode::Password => match key_event.code {
+                    KeyCode::Enter     => self.submit_signin(),
+                    KeyCode::Char(c)   => self.password_input.push(c),
+                    KeyCode::Backspace => { self.password_input.pop(); },
+                    KeyCode::Esc       => self.cancel_signin(),
+                    _ => {},
+                },
+            }
         }
     }
 }
 
-pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let constraints = match app.input_mode {
-        InputMode::Normal => vec![Constraint::Percentage(100)],
-        InputMode::Command => vec![Constraint::Min(1), Constraint::Length(1)],
-    };
+pub fn ui<B: Backend, V: VaultBackend>(f: &mut Frame<B>, app: &mut App<V>) {
     let chunks = Layout::default()
-        .constraints(constraints)
+        .constraints(vec![Constraint::Min(1), Constraint::Length(1)])
         .margin(1)
         .split(f.size());
 
     if app.app_view == AppView::ItemListView {
+        let now = Utc::now();
         let table_items = app.items
             .iter()
             .map(|item| {
-                ui::new_item_list_row(&item, &app.headers)
+                ui::new_item_list_row(item, &app.headers, &app.date_format, &now)
             });
         // FIXME: These should be calculated based on size of largest value per column and
         // use `Length` instead
-        let percentage = u16::try_from(100/app.headers.len()).unwrap();
-        let column_widths = vec![Constraint::Percentage(percentage); app.headers.len()];
+        let n_stars = app.headers.iter().filter(|header| *header == "favorite").count();
+        let percentage = u16::try_from(100 / (app.headers.len() - n_stars).max(1)).unwrap();
+        let column_widths: Vec<Constraint> = app.headers
+            .iter()
+            .map(|header| match header.as_str() {
+                // Just enough room for the star
+                "favorite" => Constraint::Length(1),
+                _ => Constraint::Percentage(percentage),
+            })
+            .collect();
         let t = Table::new(table_items)
             .header(ui::new_header_row(&app.headers))
-            .block(Block::default().borders(Borders::NONE).title("Table"))
+            .block(Block::default().borders(Borders::NONE).title(ui::loading_title(&app.item_list_title(), app.is_loading(), app.tick)))
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
             .widths(&column_widths);
         f.render_stateful_widget(t, chunks[0], &mut app.item_list_table_state);
     } else if app.app_view == AppView::ItemView {
         let item_detail_headers = vec![String::from("field"), String::from("value")];
-        let table_items = app.item_details.as_ref().unwrap().fields
-            .iter()
-            .filter(|field| { field.value.is_some() && field.label.is_some() })
-            .map(|field| {
+        let editing_i = match app.input_mode {
+            InputMode::Edit => app.item_table_state.selected(),
+            _ => None,
+        };
+        let item_details = app.item_details.as_ref().unwrap();
+        let table_items: Vec<Row> = app.item_rows()
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let (label, value) = match row {
+                    ItemRow::Summary => (
+                        item_details.category.to_string(),
+                        ui::item_summary(item_details).unwrap_or_default(),
+                    ),
+                    ItemRow::Url(url) => (String::from("website"), url.href.clone()),
+                    ItemRow::Section { section, collapsed, len } => {
+                        let heading = ui::section_heading(section.heading().unwrap_or_default(), collapsed);
+                        let value = if collapsed { format!("{} hidden", len) } else { String::from("") };
+                        return Row::new(vec![
+                            Cell::from(Span::styled(heading, Style::default().add_modifier(Modifier::BOLD))),
+                            Cell::from(Span::raw(value)),
+                        ]);
+                    },
+                    ItemRow::Field(field) => {
+                        let value = if editing_i == Some(i) {
+                            ui::edit_value(&app.edit_input, field.is_concealed())
+                        } else if let Some(totp) = field.totp() {
+                            let (code, seconds_left) = totp.now();
+                            ui::otp_value(&code, seconds_left)
+                        } else {
+                            ui::field_value(field)
+                        };
+                        // Indented under its section heading
+                        let label = match item_details.section_of(field) {
+                            Some(_) => format!("  {}", field.label.as_ref().unwrap()),
+                            None => field.label.clone().unwrap(),
+                        };
+                        (label, value)
+                    },
+                    ItemRow::File(file) => (file.name.clone(), ui::file_value(file.size)),
+                };
                 Row::new(vec![
-                    Cell::from(Span::raw(field.label.as_ref().unwrap())),
-                    Cell::from(Span::raw(field.value.as_ref().unwrap()))
+                    Cell::from(Span::raw(label)),
+                    Cell::from(Span::raw(value))
                 ])
-            });
+            })
+            .collect();
         let column_widths = vec![Constraint::Percentage(50); 2];
         let t = Table::new(table_items)
             .header(ui::new_header_row(&item_detail_headers))
-            .block(Block::default().borders(Borders::NONE).title("Entry"))
+            .block(Block::default().borders(Borders::NONE).title(ui::loading_title(&ui::item_title(item_details), app.is_loading(), app.tick)))
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
             .widths(&column_widths);
         f.render_stateful_widget(t, chunks[0], &mut app.item_table_state);
+    } else if app.app_view == AppView::ItemCreateView {
+        let item_template = app.item_template.as_ref().unwrap();
+        let editing_i = match app.input_mode {
+            InputMode::Edit => app.item_create_table_state.selected(),
+            _ => None,
+        };
+        let title_field = op::ItemDetailsField {
+            id: None,
+            r#type: None,
+            purpose: None,
+            label: Some(String::from("title")),
+            value: Some(item_template.title.clone()),
+            section: None,
+        };
+        let table_items: Vec<Row> = std::iter::once(&title_field)
+            .chain(item_template.fields.iter())
+            .enumerate()
+            .map(|(i, field)| {
+                let value = if editing_i == Some(i) {
+                    ui::edit_value(&app.edit_input, field.is_concealed())
+                } else if field.is_concealed() {
+                    "*".repeat(field.value.as_ref().map_or(0, |v| v.chars().count()))
+                } else {
+                    field.value.clone().unwrap_or_default()
+                };
+                let label = field.label.clone().or_else(|| field.id.clone()).unwrap_or_default();
+                Row::new(vec![Cell::from(Span::raw(label)), Cell::from(Span::raw(value))])
+            })
+            .collect();
+        let item_create_headers = vec![String::from("field"), String::from("value")];
+        let title = format!("New {} (`:w` to create, `q` to cancel)", item_template.category);
+        let column_widths = vec![Constraint::Percentage(50); 2];
+        let t = Table::new(table_items)
+            .header(ui::new_header_row(&item_create_headers))
+            .block(Block::default().borders(Borders::NONE).title(ui::loading_title(&title, app.is_loading(), app.tick)))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&column_widths);
+        f.render_stateful_widget(t, chunks[0], &mut app.item_create_table_state);
+    } else if app.app_view == AppView::VaultListView {
+        let vault_headers = vec![String::from("name"), String::from("id")];
+        let table_items = app.vaults
+            .iter()
+            .map(|vault| {
+                Row::new(vec![
+                    Cell::from(Span::raw(vault.name.as_str())),
+                    Cell::from(Span::raw(vault.id.as_str())),
+                ])
+            });
+        let column_widths = vec![Constraint::Percentage(50); 2];
+        let t = Table::new(table_items)
+            .header(ui::new_header_row(&vault_headers))
+            .block(Block::default().borders(Borders::NONE).title(ui::loading_title("Vaults (<Enter> to pick, `a` for all)", app.is_loading(), app.tick)))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&column_widths);
+        f.render_stateful_widget(t, chunks[0], &mut app.vault_list_table_state);
     }
     if app.input_mode == InputMode::Command {
         let input = Paragraph::new(app.cmd_input.as_ref());
         f.render_widget(input, chunks[1]);
+    } else if app.input_mode == InputMode::Edit {
+        let input = Paragraph::new("-- EDIT -- <Enter> to save, <Esc> to cancel");
+        f.render_widget(input, chunks[1]);
+    } else if let Some(message) = &app.message {
+        let line = match message {
+            Message::Info(text) => Paragraph::new(text.as_str()),
+            Message::Error(text) => Paragraph::new(text.as_str()).style(Style::default().fg(Color::Red)),
+        };
+        f.render_widget(line, chunks[1]);
+    }
+    if app.input_mode == InputMode::Confirm {
+        if let Some(confirmation) = &app.confirmation {
+            let area = ui::centered_rect(60, 3, f.size());
+            let popup = Paragraph::new(confirmation.prompt())
+                .block(Block::default().borders(Borders::ALL).title("Confirm"));
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+        }
+    } else if app.input_mode == InputMode::Generate {
+        if let Some(generator) = &app.generator {
+            let bits = generator.entropy_bits();
+            let recipe = match generator.recipe {
+                Recipe::Characters => vec![
+                    Spans::from(format!("characters (`w` for words), length {} (+/-)", generator.length)),
+                    Spans::from(format!(
+                        "{} a-z  {} A-Z  {} digits  {} symbols  {} exclude Il1O0o",
+                        ui::checkbox('a', generator.lowercase),
+                        ui::checkbox('A', generator.uppercase),
+                        ui::checkbox('d', generator.digits),
+                        ui::checkbox('s', generator.symbols),
+                        ui::checkbox('x', generator.exclude_ambiguous),
+                    )),
+                ],
+                Recipe::Words => vec![
+                    Spans::from(format!("words (`w` for characters), {} words (+/-)", generator.word_count)),
+                    Spans::from(format!("{} words in {}", generator.words.len(), app.word_file)),
+                ],
+            };
+            let mut lines = recipe;
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled(app.generated.as_str(), Style::default().add_modifier(Modifier::BOLD))));
+            lines.push(Spans::from(format!("strength: {} (~{:.0} bits)", generator::strength(bits), bits)));
+            lines.push(Spans::from("`r` reroll, `y` yank, <Enter> set password, <Esc> close"));
+            let area = ui::centered_rect(80, 8, f.size());
+            let popup = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title("Generate"))
+                .wrap(Wrap { trim: false });
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+        }
+    } else if app.input_mode == InputMode::Password {
+        if let Some(account) = &app.signin_account {
+            let prompt = match &app.signin_error {
+                Some(err) => format!("{}, try again", err),
+                None if account.is_empty() => String::from("Session expired, enter your master password"),
+                None => format!("Session for {} expired, enter your master password", account),
+            };
+            let area = ui::centered_rect(60, 6, f.size());
+            let popup = Paragraph::new(vec![
+                Spans::from(prompt),
+                Spans::from(ui::edit_value(&app.password_input, true)),
+            ])
+                .block(Block::default().borders(Borders::ALL).title("Sign in"))
+                .wrap(Wrap { trim: true });
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+        }
     }
 }
@@ -1,9 +1,11 @@
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
 use std::io::Write;
 use std::fs;
 use std::env;
 use tracing;
 
+use super::dates;
 use super::util;
 
 static CONFIG_FILENAME: &str = "tui-1password.yaml";
@@ -37,24 +39,88 @@ pub fn get_root_dir() -> String {
     // If none of the env vars point somewhere with a config file, try to make the
     // directory needed
     for fp in fps_existing {
-        if let Ok(_) = fs::create_dir_all(&fp) {
+        if fs::create_dir_all(&fp).is_ok() {
             return fp;
         }
     }
     panic!("Couldn't find or create home directory");
 }
 
+/// How `op` gets a session
+///
+/// - token_file: `export OP_SESSION_*` lines from `op signin` in `<root_dir>/token`,
+///   prompts for the master password when it's missing or expired
+/// - raw_token_file: just the token in `<root_dir>/token`, as printed by `op signin --raw`.
+///   Set as `OP_SESSION_<account>` for the `--account` in `op_args` or the first account
+/// - service_account: `OP_SERVICE_ACCOUNT_TOKEN` from the environment, never prompts
+/// - desktop_app: the 1password desktop app integration, `op` needs no session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    #[default]
+    TokenFile,
+    RawTokenFile,
+    ServiceAccount,
+    DesktopApp,
+}
+
+/// Environment variable names for item fields in `:run`, keyed by field label or
+/// id. Names for an item, by title or id, win over names for its category.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunEnv {
+    #[serde(default)]
+    pub categories: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    pub items: HashMap<String, HashMap<String, String>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppConfig {
     pub headers: Vec<String>,
     pub root_dir: String,
     pub debug: bool,
     pub clipboard_bin: String,
+    #[serde(default)]
+    pub auth: AuthMode,
+    /// Path to the 1password CLI, looked up on PATH by default
+    #[serde(default = "default_op_bin")]
+    pub op_bin: String,
+    /// Global flags passed to every `op` call, e.g. `--account` or `--config`
+    #[serde(default)]
+    pub op_args: Vec<String>,
+    /// Pass `--cache` to `op` when reading items and vaults
+    #[serde(default = "default_op_cache")]
+    pub op_cache: bool,
+    /// Word list for generated passphrases, `<root_dir>/words.txt` by default
+    #[serde(default)]
+    pub word_file: Option<String>,
+    /// List favorites above everything else whatever the list is sorted by
+    #[serde(default)]
+    pub favorites_first: bool,
+    /// strftime format for created_at/updated_at, `relative` for "3 days ago"
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Variable names item fields are exported as by `:run`
+    #[serde(default)]
+    pub run_env: RunEnv,
+}
+
+fn default_op_bin() -> String {
+    String::from("op")
+}
+
+fn default_op_cache() -> bool {
+    true
+}
+
+fn default_date_format() -> String {
+    String::from(dates::RELATIVE)
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         let headers = vec![
+            String::from("favorite"),
             String::from("title"),
             String::from("additional_information"),
             String::from("hrefs"),
@@ -62,7 +128,21 @@ impl Default for AppConfig {
         let root_dir = get_root_dir();
         let debug = true;
         let clipboard_bin = String::from("wl-copy");
-        AppConfig { headers, root_dir, debug, clipboard_bin }
+        let auth = AuthMode::default();
+        AppConfig {
+            headers,
+            root_dir,
+            debug,
+            clipboard_bin,
+            auth,
+            op_bin: default_op_bin(),
+            op_args: Vec::new(),
+            op_cache: default_op_cache(),
+            word_file: None,
+            favorites_first: false,
+            date_format: default_date_format(),
+            run_env: RunEnv::default(),
+        }
     }
 }
 
@@ -76,8 +156,8 @@ impl AppConfig {
             tracing::info!("Config file read in from: {}", &config_fp);
             let config_str = fs::read_to_string(&config_fp)
                 .unwrap_or_else(|err| panic!("Failed to read: {} - {}", &config_fp, err));
-            return serde_yaml::from_str(config_str.as_str())
-                .unwrap_or_else(|err| panic!("Couldn't deserialize: {} - {}", &config_fp, err));
+            serde_yaml::from_str(config_str.as_str())
+                .unwrap_or_else(|err| panic!("Couldn't deserialize: {} - {}", &config_fp, err))
         } else {
             tracing::info!("No config file found generating new one: {}", &config_fp);
             let ac = AppConfig::default();
@@ -86,7 +166,7 @@ impl AppConfig {
                 .unwrap_or_else(|err| panic!("Failed to create: {} - {}", &config_fp, err));
             f.write_all(s.as_bytes())
                 .unwrap_or_else(|err| panic!("Failed to write contents: {} - {}", &config_fp, err));
-            return ac;
+            ac
         }
     }
 }
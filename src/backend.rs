@@ -0,0 +1,231 @@
+/// Sources of item data that the app can be pointed at
+use chrono::Utc;
+use serde_json;
+use std::collections::HashMap;
+
+use super::err::OpError;
+use super::op::{Category, FieldAssignment, ItemDetails, ItemDetailsVault, ItemFile, ItemListEntry, ItemTemplate};
+
+/// Everything `App` needs from a password store. `op::Session` implements this
+/// by calling out to the 1password CLI, `FixtureBackend` serves canned data.
+/// Calls are made from background threads so implementations need to be `Send`.
+pub trait VaultBackend: Send + 'static {
+    /// All items visible to this backend or only those in `vault` (name or id),
+    /// equivalent to `op item list --vault`
+    fn list_items(&self, vault: Option<&str>) -> Result<Vec<ItemListEntry>, OpError>;
+    /// Every vault items can be listed from, equivalent to `op vault list`
+    fn list_vaults(&self) -> Result<Vec<ItemDetailsVault>, OpError>;
+    /// Full details of a single item looked up by id or title, only in `vault`
+    /// (name or id) if there is one, equivalent to `op item get --vault`. Titles
+    /// that more than one item has are an error without a vault to pick one.
+    fn get_item(&self, item_name: &str, vault: Option<&str>) -> Result<ItemDetails, OpError>;
+    /// Contents of an attachment or document on `item`, equivalent to `op read`
+    /// or `op document get`
+    fn get_file(&self, item: &ItemDetails, file: &ItemFile) -> Result<Vec<u8>, OpError>;
+    /// Set field values on an item and return the updated item, equivalent to `op item edit`
+    fn edit_item(&mut self, item_name: &str, assignments: &[FieldAssignment]) -> Result<ItemDetails, OpError>;
+    /// Replace every tag on an item and return the updated item, equivalent to
+    /// `op item edit --tags`
+    fn set_tags(&mut self, item_name: &str, tags: &[String]) -> Result<ItemDetails, OpError>;
+    /// Add an item to or take it out of favorites and return the updated item,
+    /// equivalent to `op item edit --favorite`
+    fn set_favorite(&mut self, item_name: &str, favorite: bool) -> Result<ItemDetails, OpError>;
+    /// Blank item of some category to fill in, equivalent to `op item template get`
+    fn get_item_template(&self, category: &str) -> Result<ItemTemplate, OpError>;
+    /// Create an item from a filled in template in `vault` (name or id), or the
+    /// default vault with `None`, equivalent to `op item create --vault` with the
+    /// template on stdin
+    fn create_item(&mut self, template: &ItemTemplate, vault: Option<&str>) -> Result<ItemDetails, OpError>;
+    /// Permanently remove an item, equivalent to `op item delete`
+    fn delete_item(&mut self, item_name: &str) -> Result<(), OpError>;
+    /// Move an item to the archive, equivalent to `op item delete --archive`
+    fn archive_item(&mut self, item_name: &str) -> Result<(), OpError>;
+    /// Names of the accounts that can be switched between, empty if there's only
+    /// ever one
+    fn accounts(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Sign in to `account` again after its session expired
+    fn signin(&mut self, _account: &str, _password: &str) -> Result<(), OpError> {
+        Ok(())
+    }
+    /// Scope every call to one account, or spread them over all accounts with `None`
+    fn set_account(&mut self, account: Option<&str>) -> Result<(), OpError> {
+        match account {
+            Some(account) => Err(OpError::UnknownAccount(String::from(account))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// In-memory backend, useful for running the app without a 1password account
+#[derive(Debug, Clone, Default)]
+pub struct FixtureBackend {
+    pub items: Vec<ItemListEntry>,
+    pub item_details: Vec<ItemDetails>,
+    pub item_templates: Vec<ItemTemplate>,
+    /// Contents of every attachment, by file id
+    pub files: HashMap<String, Vec<u8>>,
+}
+
+impl FixtureBackend {
+    pub fn new(items: Vec<ItemListEntry>, item_details: Vec<ItemDetails>) -> Self {
+        FixtureBackend { items, item_details, item_templates: Vec::new(), files: HashMap::new() }
+    }
+
+    /// Build from the json `op item list` and a json list of `op item get` outputs
+    pub fn from_json(items: &str, item_details: &str) -> Result<Self, serde_json::Error> {
+        Ok(FixtureBackend {
+            items: serde_json::from_str(items)?,
+            item_details: serde_json::from_str(item_details)?,
+            item_templates: Vec::new(),
+            files: HashMap::new(),
+        })
+    }
+
+    /// Change an item's details and carry the change over to its list entry
+    fn update_item(&mut self, item_name: &str, f: impl FnOnce(&mut ItemDetails)) -> Result<ItemDetails, OpError> {
+        let item = self.item_details
+            .iter_mut()
+            .find(|item| item.id == item_name || item.title == item_name)
+            .ok_or_else(|| not_an_item(item_name))?;
+        f(item);
+        item.version = item.version.saturating_add(1);
+        item.updated_at = Utc::now();
+        let item = item.clone();
+        for entry in self.items.iter_mut().filter(|entry| entry.id == item.id) {
+            entry.updated_at = item.updated_at;
+            entry.tags = item.tags.clone();
+            entry.favorite = item.favorite;
+        }
+        Ok(item)
+    }
+}
+
+/// Error shaped like the one `op` exits with when asked for something that isn't there
+fn not_found(msg: String) -> OpError {
+    OpError::NonZeroExit { code: Some(1), stderr: msg }
+}
+
+fn not_an_item(item_name: &str) -> OpError {
+    not_found(format!("\"{}\" isn't an item", item_name))
+}
+
+impl VaultBackend for FixtureBackend {
+    fn list_items(&self, vault: Option<&str>) -> Result<Vec<ItemListEntry>, OpError> {
+        let items: Vec<ItemListEntry> = self.items
+            .iter()
+            .filter(|item| vault.is_none_or(|v| item.vault.name == v || item.vault.id == v))
+            .cloned()
+            .collect();
+        match vault {
+            Some(vault) if items.is_empty() => Err(not_found(format!("\"{}\" isn't a vault", vault))),
+            _ => Ok(items),
+        }
+    }
+
+    /// Every vault that at least one item is in
+    fn list_vaults(&self) -> Result<Vec<ItemDetailsVault>, OpError> {
+        let mut vaults: Vec<ItemDetailsVault> = Vec::new();
+        for item in self.items.iter() {
+            if !vaults.iter().any(|vault| vault.id == item.vault.id) {
+                vaults.push(item.vault.clone());
+            }
+        }
+        Ok(vaults)
+    }
+
+    fn get_item(&self, item_name: &str, vault: Option<&str>) -> Result<ItemDetails, OpError> {
+        let matches: Vec<&ItemDetails> = self.item_details
+            .iter()
+            .filter(|item| item.id == item_name || item.title == item_name)
+            .filter(|item| vault.is_none_or(|v| item.vault.name == v || item.vault.id == v))
+            .collect();
+        match matches.as_slice() {
+            [item] => Ok((*item).clone()),
+            [] => Err(not_an_item(item_name)),
+            _ => Err(not_found(format!("More than one item matches \"{}\"", item_name))),
+        }
+    }
+
+    fn get_file(&self, item: &ItemDetails, file: &ItemFile) -> Result<Vec<u8>, OpError> {
+        self.files
+            .get(&file.id)
+            .cloned()
+            .ok_or_else(|| not_found(format!("\"{}\" isn't a file on \"{}\"", file.name, item.title)))
+    }
+
+    fn edit_item(&mut self, item_name: &str, assignments: &[FieldAssignment]) -> Result<ItemDetails, OpError> {
+        let item = self.item_details
+            .iter_mut()
+            .find(|item| item.id == item_name || item.title == item_name)
+            .ok_or_else(|| not_an_item(item_name))?;
+        for assignment in assignments {
+            let i = item.field_position(assignment)
+                .ok_or_else(|| not_found(format!("\"{}\" isn't a field", assignment.label)))?;
+            item.fields[i].value = Some(assignment.value.clone());
+        }
+        item.version = item.version.saturating_add(1);
+        item.updated_at = Utc::now();
+        Ok(item.clone())
+    }
+    fn set_tags(&mut self, item_name: &str, tags: &[String]) -> Result<ItemDetails, OpError> {
+        self.update_item(item_name, |item| item.tags = tags.to_vec())
+    }
+
+    fn set_favorite(&mut self, item_name: &str, favorite: bool) -> Result<ItemDetails, OpError> {
+        self.update_item(item_name, |item| item.favorite = favorite)
+    }
+
+    fn get_item_template(&self, category: &str) -> Result<ItemTemplate, OpError> {
+        let category = Category::from(category);
+        self.item_templates
+            .iter()
+            .find(|template| template.category == category)
+            .cloned()
+            .ok_or_else(|| not_found(format!("\"{}\" isn't a category", category)))
+    }
+
+    /// New items go to the vault of the first item unless `vault` says otherwise
+    fn create_item(&mut self, template: &ItemTemplate, vault: Option<&str>) -> Result<ItemDetails, OpError> {
+        let id: String = std::iter::repeat_with(fastrand::lowercase).take(26).collect();
+        let vault = match (vault, self.items.first()) {
+            (Some(vault), _) => self.list_vaults()?
+                .into_iter()
+                .find(|v| v.name == vault || v.id == vault)
+                .ok_or_else(|| not_found(format!("\"{}\" isn't a vault", vault)))?,
+            (None, Some(item)) => item.vault.clone(),
+            (None, None) => ItemDetailsVault { id: String::from("private"), name: String::from("Private") },
+        };
+        let item = ItemDetails {
+            id,
+            title: template.title.clone(),
+            version: 1,
+            category: template.category.clone(),
+            last_edited_by: String::from(""),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            vault,
+            fields: template.fields.clone(),
+            urls: None,
+            tags: Vec::new(),
+            favorite: false,
+            files: Vec::new(),
+            sections: template.sections.clone().and_then(|sections| serde_json::from_value(sections).ok()).unwrap_or_default(),
+        };
+        self.items.push(ItemListEntry::from(&item));
+        self.item_details.push(item.clone());
+        Ok(item)
+    }
+    fn delete_item(&mut self, item_name: &str) -> Result<(), OpError> {
+        let item = self.get_item(item_name, None)?;
+        self.items.retain(|entry| entry.id != item.id);
+        self.item_details.retain(|details| details.id != item.id);
+        Ok(())
+    }
+
+    // Archived items aren't listed so as far as the app can tell this is the same as deleting
+    fn archive_item(&mut self, item_name: &str) -> Result<(), OpError> {
+        self.delete_item(item_name)
+    }
+}
@@ -0,0 +1,161 @@
+/// Timestamp rendering and the date predicates used by `:filter`
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+use super::op;
+
+/// `date_format` that renders timestamps like "3 days ago"
+pub static RELATIVE: &str = "relative";
+
+/// `dt` rendered with `format`, a strftime format in local time, or relative to
+/// `now` for `RELATIVE`. A format chrono can't understand falls back to RFC 3339.
+pub fn format(dt: &DateTime<Utc>, format: &str, now: &DateTime<Utc>) -> String {
+    if format == RELATIVE {
+        return relative(dt, now);
+    }
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.contains(&Item::Error) {
+        return dt.to_rfc3339();
+    }
+    dt.with_timezone(&Local).format_with_items(items.into_iter()).to_string()
+}
+
+/// How long ago `dt` was, e.g. "just now", "1 hour ago" or "3 days ago"
+pub fn relative(dt: &DateTime<Utc>, now: &DateTime<Utc>) -> String {
+    let secs = (*now - *dt).num_seconds();
+    let future = secs < 0;
+    let secs = secs.abs();
+    let (n, unit) = match secs {
+        0..=59 => return String::from("just now"),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        86_400..=2_591_999 => (secs / 86_400, "day"),
+        2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    let plural = if n == 1 { "" } else { "s" };
+    if future {
+        format!("in {} {}{}", n, unit, plural)
+    } else {
+        format!("{} {}{} ago", n, unit, plural)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+/// An age like `30d` or a date like `2022-01-01`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Age(Duration),
+    Date(DateTime<Utc>),
+}
+
+/// One `:filter` predicate, e.g. `updated<30d` for items updated in the last 30
+/// days or `created>2022-01-01` for items created on or after a date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFilter {
+    pub field: DateField,
+    /// `<` when true, `>` otherwise
+    pub less: bool,
+    pub bound: Bound,
+    text: String,
+}
+
+impl DateFilter {
+    pub fn matches(&self, item: &op::ItemListEntry, now: &DateTime<Utc>) -> bool {
+        let at = match self.field {
+            DateField::Created => &item.created_at,
+            DateField::Updated => &item.updated_at,
+        };
+        // Ages compare how long ago it was so `<` means more recent, dates compare
+        // the date itself so `<` means earlier
+        match (self.bound, self.less) {
+            (Bound::Age(age), true)     => *at > *now - age,
+            (Bound::Age(age), false)    => *at <= *now - age,
+            (Bound::Date(date), true)   => *at < date,
+            (Bound::Date(date), false)  => *at >= date,
+        }
+    }
+}
+
+impl FromStr for DateFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("\"{}\" isn't a date filter, try updated<30d or created>2022-01-01", s);
+        let (split, less) = match (s.find('<'), s.find('>')) {
+            (Some(i), None) => (i, true),
+            (None, Some(i)) => (i, false),
+            _ => return Err(invalid()),
+        };
+        let field = match &s[..split] {
+            "created" | "created_at" => DateField::Created,
+            "updated" | "updated_at" => DateField::Updated,
+            _ => return Err(invalid()),
+        };
+        let value = &s[split + 1..];
+        let bound = match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(date) => Bound::Date(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())),
+            Err(_) => Bound::Age(parse_age(value).ok_or_else(invalid)?),
+        };
+        Ok(DateFilter { field, less, bound, text: String::from(s) })
+    }
+}
+
+impl fmt::Display for DateFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// `30d` style ages, in hours, days, weeks, months of 30 days or years of 365
+fn parse_age(s: &str) -> Option<Duration> {
+    let unit = s.chars().last()?;
+    let n: i64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'h' => Some(Duration::hours(n)),
+        'd' => Some(Duration::days(n)),
+        'w' => Some(Duration::weeks(n)),
+        'm' => Some(Duration::days(n * 30)),
+        'y' => Some(Duration::days(n * 365)),
+        _ => None,
+    }
+}
+
+#[test]
+fn relative_test() {
+    let now = Utc.with_ymd_and_hms(2022, 3, 1, 12, 0, 0).unwrap();
+    let ago = |duration: Duration| relative(&(now - duration), &now);
+    assert_eq!("just now", ago(Duration::seconds(59)));
+    assert_eq!("1 minute ago", ago(Duration::seconds(60)));
+    assert_eq!("5 hours ago", ago(Duration::hours(5)));
+    assert_eq!("3 days ago", ago(Duration::days(3)));
+    assert_eq!("2 months ago", ago(Duration::days(61)));
+    assert_eq!("1 year ago", ago(Duration::days(400)));
+    assert_eq!("in 1 day", ago(Duration::days(-1)));
+    assert_eq!("3 days ago", format(&(now - Duration::days(3)), RELATIVE, &now));
+    assert_eq!("2022", format(&now, "%Y", &now));
+    assert_eq!("2022-03-01T12:00:00+00:00", format(&now, "%Q", &now));
+}
+
+#[test]
+fn date_filter_test() {
+    let recent: DateFilter = "updated<30d".parse().unwrap();
+    assert_eq!(DateField::Updated, recent.field);
+    assert_eq!(Bound::Age(Duration::days(30)), recent.bound);
+    assert_eq!("updated<30d", recent.to_string());
+    let old: DateFilter = "created_at>1y".parse().unwrap();
+    assert_eq!(Bound::Age(Duration::days(365)), old.bound);
+    assert!(!old.less);
+    let since: DateFilter = "created>2022-01-01".parse().unwrap();
+    assert_eq!(Bound::Date(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap()), since.bound);
+    for invalid in ["updated", "updated<", "updated=30d", "deleted<30d", "updated<30x", "updated<d"] {
+        assert!(invalid.parse::<DateFilter>().is_err(), "{}", invalid);
+    }
+}
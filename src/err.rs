@@ -1,5 +1,9 @@
 use std::fmt;
 use std::error;
+use std::io;
+use std::str;
+
+use super::util;
 
 #[derive(Debug, Clone)]
 pub struct InvalidSessionError {
@@ -14,3 +18,144 @@ impl fmt::Display for InvalidSessionError {
         write!(f, "{}", self.msg)
     }
 }
+
+/// Ways a call to the 1password CLI can fail
+#[derive(Debug)]
+pub enum OpError {
+    /// Session token is missing or expired, holds the account that needs to sign in again
+    NotSignedIn(String),
+    /// No session in the token file for this account
+    UnknownAccount(String),
+    /// `op` couldn't be started because it isn't installed or isn't on PATH
+    BinaryMissing,
+    /// `op` ran but exited with a non-zero status
+    NonZeroExit { code: Option<i32>, stderr: String },
+    /// `op` printed something that isn't UTF-8
+    InvalidUtf8(str::Utf8Error),
+    /// Output wasn't the json that was expected
+    Parse(serde_json::Error),
+    /// Anything else that went wrong running `op` or handling its files
+    Io(io::Error),
+}
+
+impl error::Error for OpError {}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpError::NotSignedIn(account) => write!(f, "Not signed in to account \"{}\"", account),
+            OpError::UnknownAccount(account) => write!(f, "No account \"{}\" in the token file", account),
+            OpError::BinaryMissing => write!(f, "op wasn't found, is the 1password CLI installed and on PATH?"),
+            OpError::NonZeroExit { code: Some(code), stderr } => write!(f, "op exited with status {}: {}", code, stderr),
+            OpError::NonZeroExit { code: None, stderr } => write!(f, "op was killed: {}", stderr),
+            OpError::InvalidUtf8(err) => write!(f, "op output isn't valid UTF-8: {}", err),
+            OpError::Parse(err) => write!(f, "Couldn't parse op output: {}", err),
+            OpError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl OpError {
+    /// One line description short enough for the message line, `op`'s own error
+    /// without its log prefix when there is one
+    pub fn summary(&self) -> String {
+        match self {
+            OpError::NonZeroExit { stderr, .. } => String::from(util::trim_op_log_prefix(stderr)),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Error from spawning `op`, which most likely means it isn't installed
+    pub fn spawn(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => OpError::BinaryMissing,
+            _ => OpError::Io(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for OpError {
+    fn from(err: serde_json::Error) -> Self {
+        OpError::Parse(err)
+    }
+}
+
+impl From<str::Utf8Error> for OpError {
+    fn from(err: str::Utf8Error) -> Self {
+        OpError::InvalidUtf8(err)
+    }
+}
+
+impl From<io::Error> for OpError {
+    fn from(err: io::Error) -> Self {
+        OpError::Io(err)
+    }
+}
+
+/// Secret reference in a template that couldn't be filled in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    pub line: usize,
+    pub reference: String,
+    pub why: String,
+}
+
+impl fmt::Display for Unresolved {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.reference, self.why)
+    }
+}
+
+/// Ways rendering a template with `inject` can fail
+#[derive(Debug)]
+pub enum InjectError {
+    /// Reading or writing the files, or `op` failing for reasons unrelated to
+    /// the references like an expired session
+    Op(OpError),
+    /// Every reference that didn't resolve, nothing is written when there are any
+    Unresolved(Vec<Unresolved>),
+}
+
+impl error::Error for InjectError {}
+
+impl fmt::Display for InjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InjectError::Op(err) => write!(f, "{}", err),
+            InjectError::Unresolved(unresolved) => {
+                write!(f, "Couldn't resolve {} secret reference(s):", unresolved.len())?;
+                for reference in unresolved {
+                    write!(f, "\n  {}", reference)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl InjectError {
+    /// One line description for the message line, the first unresolved reference
+    /// stands in for the rest
+    pub fn summary(&self) -> String {
+        match self {
+            InjectError::Op(err) => err.summary(),
+            InjectError::Unresolved(unresolved) => match unresolved.as_slice() {
+                [first] => first.to_string(),
+                [first, rest @ ..] => format!("{} (and {} more)", first, rest.len()),
+                [] => String::new(),
+            },
+        }
+    }
+}
+
+impl From<OpError> for InjectError {
+    fn from(err: OpError) -> Self {
+        InjectError::Op(err)
+    }
+}
+
+impl From<io::Error> for InjectError {
+    fn from(err: io::Error) -> Self {
+        InjectError::Op(OpError::Io(err))
+    }
+}
@@ -0,0 +1,171 @@
+/// `:export`, the item list written out as JSON, CSV or YAML for audits
+use serde_json::{Map, Value};
+use std::io;
+
+use super::op::{ItemDetails, ItemListEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Yaml,
+}
+
+impl Format {
+    /// Format called `name`, e.g. `csv`
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Format going by the extension of `path`, JSON when it isn't a known one
+    pub fn from_path(path: &str) -> Format {
+        path.rsplit_once('.')
+            .and_then(|(_, extension)| Format::from_name(extension))
+            .unwrap_or(Format::Json)
+    }
+}
+
+/// `header` of `item` as it's exported. Timestamps are RFC 3339 and tags are a
+/// list, headers that aren't a property of the item are null.
+pub fn item_value(item: &ItemListEntry, header: &str) -> Value {
+    let optional = |value: &Option<String>| value.clone().map(Value::String).unwrap_or(Value::Null);
+    match header {
+        "id" => Value::from(item.id.as_str()),
+        "title" => Value::from(item.title.as_str()),
+        "version" => Value::from(item.version),
+        "vault" => Value::from(item.vault.name.as_str()),
+        "category" => Value::from(item.category.name()),
+        "tags" => Value::from(item.tags.clone()),
+        "favorite" => Value::from(item.favorite),
+        "created_at" => Value::from(item.created_at.to_rfc3339()),
+        "updated_at" => Value::from(item.updated_at.to_rfc3339()),
+        "last_edited_by" => Value::from(item.last_edited_by.as_str()),
+        "additional_information" => optional(&item.additional_information),
+        "hrefs" => optional(&item.hrefs),
+        "account" => optional(&item.account),
+        "urls" => item.urls
+            .iter()
+            .flatten()
+            .map(|url| Value::from(url.href.as_str()))
+            .collect(),
+        _ => Value::Null,
+    }
+}
+
+/// Every field of `item` by label, qualified with its section when it has a
+/// labelled one, e.g. `Security question 1.answer`
+fn field_values(item: &ItemDetails) -> Map<String, Value> {
+    let mut fields = Map::new();
+    for field in item.fields.iter() {
+        let label = field.label.as_deref().or(field.id.as_deref()).unwrap_or_default();
+        let name = match item.section_of(field).and_then(|section| section.heading()) {
+            Some(section) => format!("{}.{}", section, label),
+            None => String::from(label),
+        };
+        fields.entry(name).or_insert_with(|| Value::from(field.value.clone().unwrap_or_default()));
+    }
+    fields
+}
+
+/// `items` written out as `format` with a key or column per header. With
+/// `details`, one per item in the same order, every field value is included too.
+pub fn render(items: &[ItemListEntry], headers: &[String], details: Option<&[ItemDetails]>, format: Format) -> io::Result<Vec<u8>> {
+    let records: Vec<Map<String, Value>> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let mut record: Map<String, Value> = headers
+                .iter()
+                .map(|header| (header.clone(), item_value(item, header)))
+                .collect();
+            if let Some(item_details) = details.and_then(|details| details.get(i)) {
+                record.insert(String::from("fields"), Value::Object(field_values(item_details)));
+            }
+            record
+        })
+        .collect();
+    match format {
+        Format::Json => {
+            let mut out = serde_json::to_vec_pretty(&records)?;
+            out.push(b'\n');
+            Ok(out)
+        },
+        Format::Yaml => serde_yaml::to_vec(&records).map_err(io::Error::other),
+        Format::Csv => render_csv(&records, headers),
+    }
+}
+
+/// Cell that spreadsheets won't run as a formula, ones starting with a character
+/// that would start one get a leading `'`
+fn csv_cell(cell: &str) -> String {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", cell)
+    } else {
+        String::from(cell)
+    }
+}
+
+/// A column per header, then a `field.<name>` one per field name in the order
+/// they first show up, so a field labelled like a header doesn't get mixed up
+/// with it
+fn render_csv(records: &[Map<String, Value>], headers: &[String]) -> io::Result<Vec<u8>> {
+    let mut field_names: Vec<&String> = Vec::new();
+    for fields in records.iter().filter_map(|record| record.get("fields")?.as_object()) {
+        for name in fields.keys() {
+            if !field_names.contains(&name) {
+                field_names.push(name);
+            }
+        }
+    }
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let columns = headers
+        .iter()
+        .cloned()
+        .chain(field_names.iter().map(|name| format!("field.{}", name)));
+    writer.write_record(columns.map(|column| csv_cell(&column)))?;
+    for record in records {
+        let fields = record.get("fields").and_then(Value::as_object);
+        let row = headers
+            .iter()
+            .map(|header| record.get(header))
+            .chain(field_names.iter().map(|name| fields.and_then(|fields| fields.get(*name))))
+            .map(|value| match value {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(|value| value.as_str().map(String::from).unwrap_or_else(|| value.to_string()))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            })
+            .map(|cell| csv_cell(&cell));
+        writer.write_record(row)?;
+    }
+    writer.into_inner().map_err(|err| err.into_error())
+}
+
+#[test]
+fn csv_cell_test() {
+    assert_eq!("'=HYPERLINK(\"x\")", csv_cell("=HYPERLINK(\"x\")"));
+    assert_eq!("'+1", csv_cell("+1"));
+    assert_eq!("'-1", csv_cell("-1"));
+    assert_eq!("'@SUM(A1)", csv_cell("@SUM(A1)"));
+    assert_eq!("'\tx", csv_cell("\tx"));
+    assert_eq!("a=b", csv_cell("a=b"));
+    assert_eq!("", csv_cell(""));
+}
+
+#[test]
+fn format_test() {
+    assert_eq!(Some(Format::Yaml), Format::from_name("YML"));
+    assert_eq!(None, Format::from_name("xml"));
+    assert_eq!(Format::Csv, Format::from_path("~/audit/items.csv"));
+    assert_eq!(Format::Json, Format::from_path("items"));
+    assert_eq!(Format::Json, Format::from_path("items.txt"));
+}
@@ -0,0 +1,186 @@
+/// Password and passphrase generation for `:generate`
+use std::fs;
+use std::io;
+
+static LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+static UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+static DIGITS: &str = "0123456789";
+static SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.<>/?~";
+/// Characters that are easy to misread for one another
+static AMBIGUOUS: &str = "Il1O0o";
+
+/// Random characters or words from a word list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recipe {
+    Characters,
+    Words,
+}
+
+#[derive(Debug, Clone)]
+pub struct Generator {
+    pub recipe: Recipe,
+    /// Number of characters for `Recipe::Characters`
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub exclude_ambiguous: bool,
+    /// Number of words for `Recipe::Words`
+    pub word_count: usize,
+    pub separator: String,
+    /// Word list read in from the word file, empty if there isn't one
+    pub words: Vec<String>,
+}
+
+impl Generator {
+    pub fn new(words: Vec<String>) -> Self {
+        Generator {
+            recipe: Recipe::Characters,
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            word_count: 5,
+            separator: String::from("-"),
+            words,
+        }
+    }
+
+    /// Every character a `Recipe::Characters` password is drawn from
+    pub fn charset(&self) -> Vec<char> {
+        let sets = [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ];
+        sets.iter()
+            .filter(|(enabled, _)| *enabled)
+            .flat_map(|(_, set)| set.chars())
+            .filter(|c| !self.exclude_ambiguous || !AMBIGUOUS.contains(*c))
+            .collect()
+    }
+
+    /// New password or passphrase, empty if there's nothing to pick from
+    pub fn generate(&self) -> String {
+        match self.recipe {
+            Recipe::Characters => {
+                let charset = self.charset();
+                if charset.is_empty() {
+                    return String::new();
+                }
+                (0..self.length).map(|_| charset[random_index(charset.len())]).collect()
+            },
+            Recipe::Words => {
+                if self.words.is_empty() {
+                    return String::new();
+                }
+                (0..self.word_count)
+                    .map(|_| self.words[random_index(self.words.len())].as_str())
+                    .collect::<Vec<&str>>()
+                    .join(&self.separator)
+            },
+        }
+    }
+
+    /// Bits of entropy in a generated value, assuming the attacker knows the recipe
+    pub fn entropy_bits(&self) -> f64 {
+        let (choices, picks) = match self.recipe {
+            Recipe::Characters => (self.charset().len(), self.length),
+            Recipe::Words => (self.words.len(), self.word_count),
+        };
+        if choices == 0 {
+            0.0
+        } else {
+            picks as f64 * (choices as f64).log2()
+        }
+    }
+
+    pub fn add_length(&mut self, delta: i32) {
+        let n = match self.recipe {
+            Recipe::Characters => &mut self.length,
+            Recipe::Words => &mut self.word_count,
+        };
+        *n = (*n as i32 + delta).clamp(1, 128) as usize;
+    }
+}
+
+/// Rough rating of how hard a value with `bits` of entropy is to guess
+pub fn strength(bits: f64) -> &'static str {
+    if bits < 28.0 {
+        "very weak"
+    } else if bits < 36.0 {
+        "weak"
+    } else if bits < 60.0 {
+        "fair"
+    } else if bits < 128.0 {
+        "strong"
+    } else {
+        "very strong"
+    }
+}
+
+/// Words out of a word list with one word per line. Diceware lists that put the
+/// dice roll before each word are fine, only the last column is used.
+pub fn read_word_file(path: &str) -> io::Result<Vec<String>> {
+    let mut words: Vec<String> = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .map(String::from)
+        .collect();
+    words.sort();
+    words.dedup();
+    Ok(words)
+}
+
+/// Uniformly random number below `n` from the OS's secure random source
+fn random_index(n: usize) -> usize {
+    let n = n as u64;
+    // Reject the top end of the range that would favour low numbers
+    let zone = u64::MAX - (u64::MAX % n);
+    loop {
+        let mut buf = [0u8; 8];
+        getrandom::getrandom(&mut buf).expect("OS random number generator is unavailable");
+        let x = u64::from_ne_bytes(buf);
+        if x < zone {
+            return (x % n) as usize;
+        }
+    }
+}
+
+#[test]
+fn generator_charset_test() {
+    let mut generator = Generator::new(Vec::new());
+    assert_eq!(26 + 26 + 10 + SYMBOLS.len(), generator.charset().len());
+    generator.exclude_ambiguous = true;
+    assert!(!generator.charset().iter().any(|c| AMBIGUOUS.contains(*c)));
+    generator.symbols = false;
+    generator.uppercase = false;
+    generator.lowercase = false;
+    assert_eq!(vec!['2', '3', '4', '5', '6', '7', '8', '9'], generator.charset());
+    generator.digits = false;
+    assert_eq!("", generator.generate());
+    assert_eq!(0.0, generator.entropy_bits());
+}
+
+#[test]
+fn generator_generate_test() {
+    let mut generator = Generator::new(vec![String::from("apple"), String::from("pear")]);
+    generator.digits = false;
+    generator.symbols = false;
+    let password = generator.generate();
+    assert_eq!(20, password.chars().count());
+    assert!(password.chars().all(|c| c.is_ascii_alphabetic()));
+    assert_eq!(20.0 * 52f64.log2(), generator.entropy_bits());
+
+    generator.recipe = Recipe::Words;
+    generator.add_length(-1);
+    let passphrase = generator.generate();
+    assert_eq!(4, passphrase.split('-').count());
+    assert!(passphrase.split('-').all(|w| w == "apple" || w == "pear"));
+    assert_eq!(4.0, generator.entropy_bits());
+    assert_eq!("very weak", strength(generator.entropy_bits()));
+}
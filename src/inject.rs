@@ -0,0 +1,217 @@
+/// Renders templates with `{{ op://vault/item/field }}` secret references filled
+/// in, like `op inject` but through an already signed in backend
+use std::collections::HashMap;
+
+use super::backend::VaultBackend;
+use super::err::{InjectError, OpError, Unresolved};
+use super::op::{ItemDetails, ItemDetailsField};
+
+/// `op://<vault>/<item>[/<section>]/<field>` pointing at a field or attachment,
+/// each part is either a name or an id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretReference {
+    pub vault: String,
+    pub item: String,
+    pub section: Option<String>,
+    pub field: String,
+}
+
+impl SecretReference {
+    pub fn parse(reference: &str) -> Result<Self, String> {
+        let path = reference
+            .strip_prefix("op://")
+            .ok_or_else(|| String::from("doesn't start with op://"))?;
+        if path.contains('?') {
+            return Err(String::from("query parameters aren't supported"));
+        }
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.iter().any(|part| part.trim().is_empty()) {
+            return Err(String::from("should be op://<vault>/<item>[/<section>]/<field>"));
+        }
+        match parts.as_slice() {
+            [vault, item, field] => Ok(SecretReference {
+                vault: String::from(*vault),
+                item: String::from(*item),
+                section: None,
+                field: String::from(*field),
+            }),
+            [vault, item, section, field] => Ok(SecretReference {
+                vault: String::from(*vault),
+                item: String::from(*item),
+                section: Some(String::from(*section)),
+                field: String::from(*field),
+            }),
+            _ => Err(String::from("should be op://<vault>/<item>[/<section>]/<field>")),
+        }
+    }
+
+    /// Whether this points into `item`, going by its vault and item names or ids
+    fn is_in(&self, item: &ItemDetails) -> bool {
+        (self.vault == item.vault.name || self.vault == item.vault.id)
+            && (self.item == item.title || self.item == item.id)
+    }
+}
+
+/// A `{{ op://... }}` found in a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placeholder {
+    /// Byte range of the whole `{{ ... }}`
+    start: usize,
+    end: usize,
+    line: usize,
+    reference: String,
+}
+
+/// Every `{{ op://... }}` in `template`. Other `{{ ... }}` are left for whatever
+/// else reads the file.
+fn placeholders(template: &str) -> Vec<Placeholder> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(open) = template[from..].find("{{").map(|i| from + i) {
+        let close = match template[open..].find("}}") {
+            Some(i) => open + i,
+            None => break,
+        };
+        let reference = template[open + 2..close].trim();
+        if reference.starts_with("op://") {
+            found.push(Placeholder {
+                start: open,
+                end: close + 2,
+                line: template[..open].matches('\n').count() + 1,
+                reference: String::from(reference),
+            });
+        }
+        from = close + 2;
+    }
+    found
+}
+
+/// Template with its references swapped for the secrets they point at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injected {
+    pub text: String,
+    /// How many references were filled in
+    pub references: usize,
+}
+
+/// Fill in every `{{ op://... }}` in `template`. Each item is fetched with a
+/// single `get_item` however many references point into it, items already in
+/// `cached` aren't fetched at all. Nothing is returned unless every reference
+/// resolves, the error lists all of the ones that didn't.
+pub fn inject<V: VaultBackend>(backend: &V, template: &str, cached: Vec<ItemDetails>) -> Result<Injected, InjectError> {
+    let placeholders = placeholders(template);
+    let mut items = cached;
+    // Why items that couldn't be fetched couldn't be, by vault and item
+    let mut missing: HashMap<(String, String), String> = HashMap::new();
+    let mut unresolved = Vec::new();
+    let mut text = String::with_capacity(template.len());
+    let mut from = 0;
+    for placeholder in placeholders.iter() {
+        text.push_str(&template[from..placeholder.start]);
+        from = placeholder.end;
+        let unresolved_because = |why: String| Unresolved {
+            line: placeholder.line,
+            reference: placeholder.reference.clone(),
+            why,
+        };
+        let reference = match SecretReference::parse(&placeholder.reference) {
+            Ok(reference) => reference,
+            Err(why) => {
+                unresolved.push(unresolved_because(why));
+                continue;
+            },
+        };
+        let key = (reference.vault.clone(), reference.item.clone());
+        if !items.iter().any(|item| reference.is_in(item)) && !missing.contains_key(&key) {
+            // An expired session or missing `op` stops everything, nothing else would resolve
+            match fetch(backend, &reference)? {
+                Ok(item) => items.push(item),
+                Err(why) => {
+                    missing.insert(key.clone(), why);
+                },
+            }
+        }
+        let resolved = match items.iter().find(|item| reference.is_in(item)) {
+            Some(item) => resolve(backend, item, &reference),
+            None => Err(missing[&key].clone()),
+        };
+        match resolved {
+            Ok(secret) => text.push_str(&secret),
+            Err(why) => unresolved.push(unresolved_because(why)),
+        }
+    }
+    text.push_str(&template[from..]);
+    if unresolved.is_empty() {
+        Ok(Injected { text, references: placeholders.len() })
+    } else {
+        Err(InjectError::Unresolved(unresolved))
+    }
+}
+
+/// Item `reference` points into, looked up in its vault so items with the same
+/// title in other vaults don't get in the way. `op` not finding it is a reason
+/// the reference doesn't resolve, any other failure stops the whole template.
+fn fetch<V: VaultBackend>(backend: &V, reference: &SecretReference) -> Result<Result<ItemDetails, String>, OpError> {
+    match backend.get_item(&reference.item, Some(&reference.vault)) {
+        Ok(item) if reference.is_in(&item) => Ok(Ok(item)),
+        Ok(item) => Ok(Err(format!("\"{}\" is in vault \"{}\", not \"{}\"", reference.item, item.vault.name, reference.vault))),
+        Err(err @ OpError::NonZeroExit { .. }) => Ok(Err(err.summary())),
+        Err(err) => Err(err),
+    }
+}
+
+/// Value of the field or contents of the attachment `reference` points at. Fields
+/// can be named by label or id, a label has to pick out a single field.
+fn resolve<V: VaultBackend>(backend: &V, item: &ItemDetails, reference: &SecretReference) -> Result<String, String> {
+    let in_section = |field: &&ItemDetailsField| match &reference.section {
+        Some(section) => {
+            field.section.as_ref().is_some_and(|s| &s.id == section)
+                || item.section_of(field).and_then(|s| s.heading()) == Some(section.as_str())
+        },
+        None => true,
+    };
+    if let Some(field) = item.fields.iter().filter(in_section).find(|field| field.id.as_ref() == Some(&reference.field)) {
+        return Ok(field.value.clone().unwrap_or_default());
+    }
+    let labelled: Vec<&ItemDetailsField> = item.fields
+        .iter()
+        .filter(in_section)
+        .filter(|field| field.label.as_ref() == Some(&reference.field))
+        .collect();
+    match labelled.as_slice() {
+        [field] => return Ok(field.value.clone().unwrap_or_default()),
+        [] => {},
+        _ => return Err(format!("more than one field in \"{}\" is called \"{}\", use its id", item.title, reference.field)),
+    }
+    if reference.section.is_none() {
+        if let Some(file) = item.files.iter().find(|file| file.name == reference.field || file.id == reference.field) {
+            let contents = backend.get_file(item, file).map_err(|err| err.summary())?;
+            return String::from_utf8(contents).map_err(|_| format!("\"{}\" isn't text", file.name));
+        }
+    }
+    Err(format!("no field \"{}\" in \"{}\"", reference.field, item.title))
+}
+
+#[test]
+fn placeholders_test() {
+    let template = "A={{ op://v/i/f }}\nB={{op://v/i/s/f}} {{ not a reference }}\nC={{ op://v/i/f";
+    let found = placeholders(template);
+    assert_eq!(2, found.len());
+    assert_eq!("op://v/i/f", found[0].reference);
+    assert_eq!((2, 18, 1), (found[0].start, found[0].end, found[0].line));
+    assert_eq!("op://v/i/s/f", found[1].reference);
+    assert_eq!(2, found[1].line);
+}
+
+#[test]
+fn secret_reference_parse_test() {
+    let reference = SecretReference::parse("op://Work/aws root/Security question 1/answer").unwrap();
+    assert_eq!("Work", reference.vault);
+    assert_eq!("aws root", reference.item);
+    assert_eq!(Some(String::from("Security question 1")), reference.section);
+    assert_eq!("answer", reference.field);
+    assert_eq!(None, SecretReference::parse("op://Personal/GitHub/password").unwrap().section);
+    for invalid in ["op://Personal/GitHub", "op://Personal//password", "op://a/b/c/d/e", "op://a/b/c?attribute=otp", "a/b/c"] {
+        assert!(SecretReference::parse(invalid).is_err(), "{}", invalid);
+    }
+}
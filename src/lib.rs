@@ -1,28 +1,56 @@
 /// CLI entry point
 use crossterm::event;
-use std::io;
+use std::env;
 use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::time::Duration;
 use tracing::{Level};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tui::{
-    backend::{Backend},
-    Terminal};
+
+use backend::VaultBackend;
 
 pub mod app;
 pub mod app_config;
+pub mod backend;
+pub mod dates;
 pub mod err;
+pub mod export;
+pub mod generator;
+pub mod inject;
 pub mod op;
+pub mod run;
 pub mod terminal;
+pub mod totp;
 pub mod ui;
 pub mod util;
+pub mod worker;
 
-fn draw_app<B: Backend>(terminal: &mut Terminal<B>, mut app: app::App) -> io::Result<()> {
+fn draw_app<V: VaultBackend>(tm: &mut terminal::TerminalModifier, mut app: app::App<V>) -> Result<(), Box<dyn Error>> {
     loop {
-        terminal.draw(|f| app::ui(f, &mut app))?;
+        tm.terminal.draw(|f| app::ui(f, &mut app))?;
 
-        app.handle_event(event::read()?);
+        // Wake up regularly even without input so finished background work is
+        // picked up and the loading indicator keeps moving
+        if event::poll(Duration::from_millis(100))? {
+            app.handle_event(event::read()?);
+        }
+        if let Some(pending) = app.pending_run.take() {
+            tm.suspend()?;
+            let status = pending.status();
+            // Leave the output up until it's been read
+            print!("\nPress Enter to go back to tui-1password");
+            io::stdout().flush()?;
+            io::stdin().read_line(&mut String::new())?;
+            tm.resume()?;
+            app.finish_run(&pending.command, status);
+        }
+        app.on_tick();
         if !app.is_running {
-            return Ok(());
+            return match app.exit_error.take() {
+                Some(err) => Err(err.into()),
+                None => Ok(()),
+            };
         }
     }
 }
@@ -42,6 +70,14 @@ pub fn run() -> Result<(), Box<dyn Error>> {
         .init();
     // FIXME: setup_tracing END
     let config = app_config::AppConfig::new(root_dir);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("inject") {
+        // Returned rather than exiting here so the log is flushed when `_guard` drops
+        return run_inject(config, &args[1..]).map_err(|err| {
+            tracing::error!("inject failed: {}", err);
+            err
+        });
+    }
     tracing::info!("Starting new instance tui-1password instance with config: {:?}", config);
 
     // create app and run it
@@ -51,7 +87,9 @@ pub fn run() -> Result<(), Box<dyn Error>> {
 
             let mut tm = terminal::TerminalModifier::new()?;
             // Loop forever, if return, there's an error
-            let res = draw_app(&mut tm.terminal, app);
+            let res = draw_app(&mut tm, app);
+            // Restore the terminal first so the error isn't printed to the alternate screen
+            drop(tm);
             if let Err(err) = res {
                 eprintln!("{}", err);
                 tracing::error!("App loop ended and returned error: {:?}", err);
@@ -64,3 +102,38 @@ pub fn run() -> Result<(), Box<dyn Error>> {
     };
     Ok(())
 }
+
+/// `tui-1password inject [-i <template>] [-o <output>]`, fills in the `{{ op://... }}`
+/// references in a template like `op inject`. Reads stdin and writes stdout when
+/// the files aren't given, output files are only readable by the current user.
+fn run_inject(config: app_config::AppConfig, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: tui-1password inject [-i <template>] [-o <output>]";
+    let mut in_file = None;
+    let mut out_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-i" | "--in-file" => in_file = Some(args.next().ok_or(usage)?),
+            "-o" | "--out-file" => out_file = Some(args.next().ok_or(usage)?),
+            _ => return Err(format!("unexpected argument \"{}\", {}", arg, usage).into()),
+        }
+    }
+    let template = match in_file {
+        Some(path) => fs::read_to_string(path).map_err(|err| format!("couldn't read {}: {}", path, err))?,
+        None => {
+            let mut template = String::new();
+            io::stdin().read_to_string(&mut template)?;
+            template
+        },
+    };
+    let token_path = format!("{}/token", config.root_dir);
+    let accounts = op::Accounts::from_auth(config.auth, token_path, &op::OpCommand::from(&config))?;
+    let injected = inject::inject(&accounts, &template, Vec::new())?;
+    match out_file {
+        Some(path) => util::write_private_file(path, injected.text.as_bytes())
+            .map_err(|err| format!("couldn't write {}: {}", path, err))?,
+        None => io::stdout().write_all(injected.text.as_bytes())?,
+    }
+    tracing::info!("Injected {} secret references", injected.references);
+    Ok(())
+}
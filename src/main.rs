@@ -1,7 +1,9 @@
 /// CLI entry point
-use std::error::Error;
-use tui_1password;
+use std::process;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    tui_1password::run()
+fn main() {
+    if let Err(err) = tui_1password::run() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
 }
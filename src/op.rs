@@ -1,34 +1,227 @@
 /// Interface to 1password
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::env;
 use std::error;
+use std::fmt;
 use std::fs;
-use std::fs::File;
 use std::io;
-use std::io::{BufReader, BufRead, Write};
-use std::process::{Command, Stdio};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
 use std::str;
 use std::time::Duration;
 use rpassword;
 
-use super::err;
+use super::app_config::{AppConfig, AuthMode};
+use super::backend::VaultBackend;
+use super::err::{self, OpError};
+use super::totp::Totp;
+use super::util;
 
 // Temporary tokens from `op signin` last for 30 minutes
 const OP_TOKEN_TTL: u64 = 1800;
 
+// Env var `op` reads a service account's token from
+const SERVICE_ACCOUNT_VAR: &str = "OP_SERVICE_ACCOUNT_TOKEN";
+
+// Bits of `op` error messages that mean the session token is no good anymore
+const NOT_SIGNED_IN_ERRORS: [&str; 4] = [
+    "not currently signed in",
+    "session expired",
+    "authentication required",
+    "invalid session token",
+];
+
+/// How a session's token is handed to `op`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Credential {
+    /// Set as the env var `name`, `OP_SESSION_<account>` or `OP_SERVICE_ACCOUNT_TOKEN`
+    EnvVar,
+    /// Token from `op signin --raw`, which doesn't say which account it's for.
+    /// Set as `OP_SESSION_<account>` like `EnvVar` once `name_raw_account` has
+    /// worked out the account, never passed with `--session` where `ps` shows it.
+    RawToken,
+    /// Nothing is passed, `op` asks the 1password desktop app
+    DesktopApp,
+}
+
+/// Entry of `op account list`, only what's needed to name a raw token's env var
+#[derive(Debug, Deserialize)]
+struct AccountListEntry {
+    user_uuid: String,
+}
+
+/// Binary and flags every `op` call is made with
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpCommand {
+    pub bin: String,
+    /// Passed before the subcommand, e.g. `--account` or `--config`
+    pub global_args: Vec<String>,
+    /// Pass `--cache` on reads
+    pub cache: bool,
+}
+
+impl Default for OpCommand {
+    fn default() -> Self {
+        OpCommand { bin: String::from("op"), global_args: Vec::new(), cache: true }
+    }
+}
+
+impl From<&AppConfig> for OpCommand {
+    fn from(config: &AppConfig) -> Self {
+        OpCommand {
+            bin: config.op_bin.clone(),
+            global_args: config.op_args.clone(),
+            cache: config.op_cache,
+        }
+    }
+}
+
+impl OpCommand {
+    /// `op` with the global args set, ready for a subcommand. `cacheable` is for
+    /// reads that can use `--cache` if it's turned on.
+    pub fn command(&self, cacheable: bool) -> Command {
+        let mut cmd = Command::new(&self.bin);
+        cmd.args(&self.global_args);
+        if cacheable && self.cache {
+            cmd.arg("--cache");
+        }
+        cmd
+    }
+}
+
 #[derive(Debug)]
 pub struct Session {
     pub name: String,
     pub token: String,
+    pub credential: Credential,
+    pub op: OpCommand,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemUrl {
     label: Option<String>,
     primary: Option<bool>,
     pub href: String,
 }
 
+/// Kind of item, which decides the fields it starts out with. `Unknown` keeps
+/// categories added to 1password after this was written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Category {
+    Login,
+    Password,
+    ApiCredential,
+    Server,
+    Database,
+    CreditCard,
+    Membership,
+    Passport,
+    SoftwareLicense,
+    OutdoorLicense,
+    SecureNote,
+    WirelessRouter,
+    BankAccount,
+    DriverLicense,
+    Identity,
+    RewardProgram,
+    Document,
+    EmailAccount,
+    SocialSecurityNumber,
+    MedicalRecord,
+    SshKey,
+    CryptoWallet,
+    Custom,
+    Unknown(String),
+}
+
+/// Every known category with the name `op` uses and the name shown on screen
+static CATEGORIES: [(Category, &str, &str); 23] = [
+    (Category::Login, "LOGIN", "Login"),
+    (Category::Password, "PASSWORD", "Password"),
+    (Category::ApiCredential, "API_CREDENTIAL", "API Credential"),
+    (Category::Server, "SERVER", "Server"),
+    (Category::Database, "DATABASE", "Database"),
+    (Category::CreditCard, "CREDIT_CARD", "Credit Card"),
+    (Category::Membership, "MEMBERSHIP", "Membership"),
+    (Category::Passport, "PASSPORT", "Passport"),
+    (Category::SoftwareLicense, "SOFTWARE_LICENSE", "Software License"),
+    (Category::OutdoorLicense, "OUTDOOR_LICENSE", "Outdoor License"),
+    (Category::SecureNote, "SECURE_NOTE", "Secure Note"),
+    (Category::WirelessRouter, "WIRELESS_ROUTER", "Wireless Router"),
+    (Category::BankAccount, "BANK_ACCOUNT", "Bank Account"),
+    (Category::DriverLicense, "DRIVER_LICENSE", "Driver License"),
+    (Category::Identity, "IDENTITY", "Identity"),
+    (Category::RewardProgram, "REWARD_PROGRAM", "Reward Program"),
+    (Category::Document, "DOCUMENT", "Document"),
+    (Category::EmailAccount, "EMAIL_ACCOUNT", "Email Account"),
+    (Category::SocialSecurityNumber, "SOCIAL_SECURITY_NUMBER", "Social Security Number"),
+    (Category::MedicalRecord, "MEDICAL_RECORD", "Medical Record"),
+    (Category::SshKey, "SSH_KEY", "SSH Key"),
+    (Category::CryptoWallet, "CRYPTO_WALLET", "Crypto Wallet"),
+    (Category::Custom, "CUSTOM", "Custom"),
+];
+
+impl Category {
+    /// Name `op` uses, e.g. `CREDIT_CARD`
+    pub fn as_op_str(&self) -> &str {
+        match self {
+            Category::Unknown(category) => category,
+            known => CATEGORIES.iter().find(|(c, _, _)| c == known).map_or("", |(_, op_str, _)| op_str),
+        }
+    }
+
+    /// Name shown on screen, e.g. `Credit Card`
+    pub fn name(&self) -> &str {
+        match self {
+            Category::Unknown(category) => category,
+            known => CATEGORIES.iter().find(|(c, _, _)| c == known).map_or("", |(_, _, name)| name),
+        }
+    }
+
+    /// Ids of the fields shown above the rest in the item view, in order
+    pub fn leading_fields(&self) -> &'static [&'static str] {
+        match self {
+            Category::Login => &["username", "password"],
+            Category::CreditCard => &["cardholder", "type", "ccnum", "cvv", "expiry", "validFrom"],
+            Category::SshKey => &["key_type", "fingerprint", "public_key", "private_key"],
+            _ => &[],
+        }
+    }
+}
+
+/// Accepts `op`'s names along with what's shown on screen or typed in a command,
+/// `credit card`, `Credit Card` and `CREDIT_CARD` are all the same
+impl From<&str> for Category {
+    fn from(category: &str) -> Self {
+        let op_str = category.trim().to_uppercase().replace(' ', "_");
+        CATEGORIES
+            .iter()
+            .find(|(_, c, _)| *c == op_str)
+            .map_or_else(|| Category::Unknown(String::from(category)), |(c, _, _)| c.clone())
+    }
+}
+
+impl From<String> for Category {
+    fn from(category: String) -> Self {
+        Category::from(category.as_str())
+    }
+}
+
+impl From<Category> for String {
+    fn from(category: Category) -> Self {
+        String::from(category.as_op_str())
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemDetailsVault {
     pub id: String,
@@ -36,33 +229,104 @@ pub struct ItemDetailsVault {
 }
 
 /// Struct representing each element in the json list returned by `op item list`
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemListEntry {
     pub id: String,
     pub title: String,
     pub version: u8,
     pub vault: ItemDetailsVault,
-    // FIXME: Should be an enum
-    pub category: String,
+    pub category: Category,
 
-    // FIXME: should be date-time
     pub last_edited_by: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub additional_information: Option<String>,
     pub urls: Option<Vec<ItemUrl>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// `op` leaves this out unless it's true
+    #[serde(default)]
+    pub favorite: bool,
 
     pub index_term: Option<String>,
-    pub hrefs: Option<String>
+    pub hrefs: Option<String>,
+    /// Filled in by `Accounts` with the account the item was listed from
+    pub account: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemDetailsField {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub r#type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Only the id is guaranteed to be filled in, the label lives in `ItemDetails::sections`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<ItemSection>,
+}
+
+/// Group of fields, `op` puts fields that were never moved into one in an
+/// unlabelled section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemSection {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub label: Option<String>,
-    pub value: Option<String>
+}
+
+impl ItemSection {
+    /// Label to show above the section's fields, `None` for unlabelled sections
+    pub fn heading(&self) -> Option<&str> {
+        self.label.as_deref().filter(|label| !label.is_empty())
+    }
+}
+
+impl ItemDetailsField {
+    /// Values of these fields should never be shown on screen as-is
+    pub fn is_concealed(&self) -> bool {
+        self.r#type.as_deref() == Some("CONCEALED")
+    }
+    /// One-time password fields hold an `otpauth://` URI instead of a code
+    pub fn is_otp(&self) -> bool {
+        self.r#type.as_deref() == Some("OTP")
+    }
+    /// Code generator for an OTP field, `None` for other fields or if the URI doesn't parse
+    pub fn totp(&self) -> Option<Totp> {
+        if self.is_otp() {
+            Totp::from_uri(self.value.as_ref()?)
+        } else {
+            None
+        }
+    }
+}
+
+/// New value for a field of an item, see `VaultBackend::edit_item`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldAssignment {
+    /// Label of the section the field is in, needed when labels repeat across sections
+    pub section: Option<String>,
+    pub label: String,
+    pub value: String,
+}
+
+impl FieldAssignment {
+    pub fn new(label: &str, value: &str) -> Self {
+        FieldAssignment { section: None, label: String::from(label), value: String::from(value) }
+    }
+
+    /// Assignment to `field` of `item`, qualified with its section if it has a label
+    pub fn for_field(item: &ItemDetails, field: &ItemDetailsField, value: &str) -> Self {
+        FieldAssignment {
+            section: item.section_of(field).and_then(|section| section.heading()).map(String::from),
+            ..FieldAssignment::new(field.label.as_deref().unwrap_or_default(), value)
+        }
+    }
 }
 
 /// Struct representing the json map returned by `op item get`
@@ -71,19 +335,103 @@ pub struct ItemDetails {
     pub id: String,
     pub title: String,
     pub version: u8,
-    // FIXME: Should be an enum
-    pub category: String,
+    pub category: Category,
 
-    // FIXME: should be date-time
     pub last_edited_by: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 
     pub vault: ItemDetailsVault,
     pub fields: Vec<ItemDetailsField>,
+    pub urls: Option<Vec<ItemUrl>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    /// Attachments, or the document itself for `DOCUMENT` items
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<ItemFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<ItemSection>,
+}
+
+/// Entry in the `files` list of `op item get`, the contents are fetched separately
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemFile {
+    pub id: String,
+    pub name: String,
+    /// Bytes
+    #[serde(default)]
+    pub size: u64,
+    pub content_path: Option<String>,
+}
+
+/// Struct representing the json map returned by `op item template get`, filled in
+/// and passed back to `op item create`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    #[serde(default)]
+    pub title: String,
+    pub category: Category,
+    pub fields: Vec<ItemDetailsField>,
+    // Not used by the app, kept so fields pointing at a section still line up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sections: Option<serde_json::Value>,
 }
 
 impl ItemDetails {
+    /// Labelled section `field` is in, `None` if it isn't in one
+    pub fn section_of(&self, field: &ItemDetailsField) -> Option<&ItemSection> {
+        let id = &field.section.as_ref()?.id;
+        self.sections
+            .iter()
+            .find(|section| &section.id == id)
+            .filter(|section| section.heading().is_some())
+    }
+
+    /// Index in `fields` of the field `assignment` is for
+    pub fn field_position(&self, assignment: &FieldAssignment) -> Option<usize> {
+        self.fields.iter().position(|field| {
+            field.label.as_ref() == Some(&assignment.label)
+                && self.section_of(field).and_then(|section| section.heading()) == assignment.section.as_deref()
+        })
+    }
+
+    /// `op://<vault>/<item>[/<section>]/<field>` secret reference to `field`, for
+    /// config files read with `op read`, `op inject` or `op run`. The field is
+    /// qualified with its section when it has a labelled one.
+    pub fn secret_reference(&self, field: &ItemDetailsField) -> String {
+        let section = self.section_of(field);
+        let in_same_section = |other: &&ItemDetailsField| {
+            self.section_of(other).map(|s| &s.id) == section.map(|s| &s.id)
+        };
+        // A label shared with another field in the section can't tell them apart
+        let is_unique = self.fields.iter().filter(in_same_section).filter(|other| other.label == field.label).count() == 1;
+        let label = field.label.as_deref().filter(|_| is_unique);
+        let mut reference = self.reference_prefix();
+        if let Some(section) = section {
+            reference.push_str(reference_segment(section.heading(), &section.id));
+            reference.push('/');
+        }
+        reference.push_str(reference_segment(label, field.id.as_deref().unwrap_or_default()));
+        reference
+    }
+
+    /// `op://<vault>/<item>/<file>` secret reference to an attachment
+    pub fn file_reference(&self, file: &ItemFile) -> String {
+        let mut reference = self.reference_prefix();
+        reference.push_str(reference_segment(Some(&file.name), &file.id));
+        reference
+    }
+
+    fn reference_prefix(&self) -> String {
+        format!(
+            "op://{}/{}/",
+            reference_segment(Some(&self.vault.name), &self.vault.id),
+            reference_segment(Some(&self.title), &self.id),
+        )
+    }
+
     pub fn fill_none_fields(&mut self) {
 		for field in self.fields.iter_mut() {
 			match field.value {
@@ -96,6 +444,47 @@ impl ItemDetails {
     }
 }
 
+/// `name` if it can go in a secret reference as-is, otherwise `id`. `op` only
+/// allows letters, digits, `-`, `_`, `.` and spaces in names, anything else like a
+/// slash would be read as a separator.
+fn reference_segment<'a>(name: Option<&'a str>, id: &'a str) -> &'a str {
+    let is_allowed = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == ' ';
+    match name {
+        Some(name) if !name.trim().is_empty() && name.chars().all(is_allowed) => name,
+        _ => id,
+    }
+}
+
+impl From<&ItemDetails> for ItemListEntry {
+    /// List entry for an item that was just created or fetched, `op item list`
+    /// shows the username as the additional information for logins
+    fn from(item: &ItemDetails) -> Self {
+        let additional_information = item.fields
+            .iter()
+            .find(|field| field.purpose.as_deref() == Some("USERNAME"))
+            .and_then(|field| field.value.clone());
+        let mut entry = ItemListEntry {
+            id: item.id.clone(),
+            title: item.title.clone(),
+            version: item.version,
+            vault: item.vault.clone(),
+            category: item.category.clone(),
+            last_edited_by: item.last_edited_by.clone(),
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+            additional_information,
+            urls: item.urls.clone(),
+            tags: item.tags.clone(),
+            favorite: item.favorite,
+            index_term: None,
+            hrefs: None,
+            account: None,
+        };
+        entry.gen_index_term();
+        entry
+    }
+}
+
 impl ItemListEntry {
     pub fn gen_index_term(&mut self) {
         match self.index_term {
@@ -132,106 +521,672 @@ impl ItemListEntry {
 }
 
 impl Session {
-    fn signin(token_path: &String) -> Result<(),io::Error> {
-		let token_file = File::create(token_path).unwrap();
-		let token_stdio = Stdio::from(token_file);
-        let password = rpassword::prompt_password("Enter your 1password master password: ").unwrap();
-        match Command::new("op")
+    /// Pass `password` to `op signin` and write the resulting token file to `token_path`
+    pub fn signin(op: &OpCommand, token_path: &str, password: &str) -> Result<(), OpError> {
+        Session::signin_to_file(op, token_path, password, false)
+    }
+    /// Same as `signin` but the token file only holds the token, see `from_raw_token_file`
+    pub fn signin_raw(op: &OpCommand, token_path: &str, password: &str) -> Result<(), OpError> {
+        Session::signin_to_file(op, token_path, password, true)
+    }
+    fn signin_to_file(op: &OpCommand, token_path: &str, password: &str, raw: bool) -> Result<(), OpError> {
+        let mut child = op.command(false)
             .arg("signin")
+            .args(if raw { &["--raw"][..] } else { &[][..] })
             .arg("-f")
             .stdin(Stdio::piped())
-            .stdout(token_stdio)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
-        {
-            Ok(mut child) => {
-                child.stdin.as_ref().unwrap().write(password.as_bytes()).unwrap();
-                child.wait().unwrap();
-                Ok(())
-            },
-            Err(e) => {
-                Err(e)
-            }
+            .map_err(OpError::spawn)?;
+        // Dropping stdin closes it so `op` stops waiting on more input
+        child.stdin.take().unwrap().write_all(password.as_bytes())?;
+        let output = child.wait_with_output()?;
+        // A failed sign in leaves whatever token file was there before alone
+        if !output.status.success() {
+            let stderr = String::from(String::from_utf8_lossy(&output.stderr).trim());
+            return Err(OpError::NonZeroExit { code: output.status.code(), stderr });
         }
+        util::write_private_file(token_path, &output.stdout)?;
+        Ok(())
     }
     /// True if cached token exists and created less than OP_TOKEN_TTL seconds ago
     pub fn is_active_token_file(path: &String) -> bool {
         if let Ok(metadata) = fs::metadata(path) {
             let token_age = metadata.modified().unwrap().elapsed().unwrap();
-            return token_age < Duration::from_secs(OP_TOKEN_TTL);
+            token_age < Duration::from_secs(OP_TOKEN_TTL)
         } else {
-            return false;
-        }
-    }
-    // Currently only takes the first entry in the token
-    pub fn from_token_file(path: &String) -> Result<Self, Box<dyn error::Error>> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let mut name = String::from("");
-        let mut token = String::from("");
-        for line in reader.lines().map(|l| l.unwrap()) {
-            // FIXME: should handle just the token as well (output when --raw used)
-            if line.contains("export") {
-                let splits: Vec<&str> = line.split("=").collect();
-                assert_eq!(2, splits.len());
-                // strip out the "export "
-                name = String::from(&(splits[0])[7..]);
-                // strip out the quotes surrounding the token
-                token = String::from(&(splits[1])[1..splits[1].len() - 1]);
-            }
+            false
         }
-        if name == "" && token == "" {
-            return Err(err::InvalidSessionError{
+    }
+    /// Session out of an `export OP_SESSION_*="token"` line printed by `op signin`,
+    /// `None` for anything else
+    fn from_export_line(line: &str) -> Option<Self> {
+        let (name, token) = line.trim().strip_prefix("export ")?.split_once('=')?;
+        let name = name.trim();
+        let token = token.trim().trim_matches('"');
+        if name.is_empty() || token.is_empty() {
+            return None;
+        }
+        Some(Session {
+            name: String::from(name),
+            token: String::from(token),
+            credential: Credential::EnvVar,
+            op: OpCommand::default(),
+        })
+    }
+    /// One session per `export OP_SESSION_*` line in the token file, in the order
+    /// they appear. Signing in to several accounts appends a line for each. The
+    /// file can be edited by hand so lines that aren't exports are skipped.
+    pub fn all_from_token_file(path: &str) -> Result<Vec<Self>, Box<dyn error::Error>> {
+        let contents = fs::read(path)?;
+        let sessions: Vec<Session> = contents
+            .split(|&b| b == b'\n')
+            .filter_map(|line| str::from_utf8(line).ok())
+            .filter_map(Session::from_export_line)
+            .collect();
+        if sessions.is_empty() {
+            Err(err::InvalidSessionError{
                 msg: String::from("op failed to sign in. Please check your password and try again")
+            }.into())
+        } else {
+            Ok(sessions)
+        }
+    }
+    /// First session in the token file
+    pub fn from_token_file(path: &str) -> Result<Self, Box<dyn error::Error>> {
+        Ok(Session::all_from_token_file(path)?.remove(0))
+    }
+    /// Session out of a file holding nothing but the token, what `op signin --raw`
+    /// prints. There's no account name in it, see `name_raw_account`.
+    pub fn from_raw_token_file(path: &str) -> Result<Self, Box<dyn error::Error>> {
+        let token = String::from(fs::read_to_string(path)?.trim());
+        if token.is_empty() || token.contains(char::is_whitespace) {
+            return Err(err::InvalidSessionError{
+                msg: format!("{} doesn't hold a raw session token", path)
             }.into());
+        }
+        Ok(Session { name: String::new(), token, credential: Credential::RawToken, op: OpCommand::default() })
+    }
+    /// Session for the service account whose token is in `OP_SERVICE_ACCOUNT_TOKEN`
+    pub fn from_service_account_env() -> Result<Self, Box<dyn error::Error>> {
+        match env::var(SERVICE_ACCOUNT_VAR) {
+            Ok(token) if !token.is_empty() => Ok(Session {
+                name: String::from(SERVICE_ACCOUNT_VAR),
+                token,
+                credential: Credential::EnvVar,
+                op: OpCommand::default(),
+            }),
+            _ => Err(err::InvalidSessionError{
+                msg: format!("{} isn't set", SERVICE_ACCOUNT_VAR)
+            }.into()),
+        }
+    }
+    /// Session that leaves signing in to the 1password desktop app integration
+    pub fn desktop_app() -> Self {
+        Session {
+            name: String::new(),
+            token: String::new(),
+            credential: Credential::DesktopApp,
+            op: OpCommand::default(),
+        }
+    }
+    /// Replace the token file with the current tokens of `sessions`, in the same
+    /// format each was read in
+    pub fn write_token_file(path: &str, sessions: &[Session]) -> Result<(), io::Error> {
+        let contents: String = sessions
+            .iter()
+            .filter_map(|session| match session.credential {
+                Credential::EnvVar => Some(format!("export {}=\"{}\"\n", session.name, session.token)),
+                Credential::RawToken => Some(format!("{}\n", session.token)),
+                Credential::DesktopApp => None,
+            })
+            .collect();
+        util::write_private_file(path, contents.as_bytes())
+    }
+    /// Session out of a raw token file, signing in first if there isn't one. There's
+    /// no telling how old a raw token is so it's used as long as it's there, an
+    /// expired one gets refreshed in the app.
+    pub fn new_raw(token_path: String, op: &OpCommand) -> Result<Self, Box<dyn error::Error>> {
+        if let Ok(session) = Session::from_raw_token_file(&token_path) {
+            return Ok(session);
+        }
+        let password = rpassword::prompt_password("Enter your 1password master password: ").unwrap();
+        Session::signin_raw(op, &token_path, &password)?;
+        Session::from_raw_token_file(&token_path)
+    }
+
+    /// Name a raw token session `OP_SESSION_<account>` after the account `op` is
+    /// going to use: the `--account` in its global args, otherwise the first one
+    /// `op account list` knows about
+    pub fn name_raw_account(&mut self) -> Result<(), OpError> {
+        let args = &self.op.global_args;
+        let flag = args.iter().enumerate().find_map(|(i, arg)| match arg.strip_prefix("--account") {
+            Some("") => args.get(i + 1).cloned(),
+            Some(account) => account.strip_prefix('=').map(String::from),
+            None => None,
+        });
+        let account = match flag {
+            Some(account) => account,
+            None => {
+                let accounts: Vec<AccountListEntry> = serde_json::from_str(&self.run(self.op.command(false)
+                                                                                       .arg("account")
+                                                                                       .arg("list")
+                                                                                       .arg("--format=json"))?)?;
+                accounts
+                    .into_iter()
+                    .next()
+                    .map(|account| account.user_uuid)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "op has no accounts, add one with `op account add`"))?
+            },
+        };
+        self.name = format!("OP_SESSION_{}", account);
+        Ok(())
+    }
+
+    /// Account shorthand or user id that `op` put at the end of the env var name
+    pub fn account(&self) -> &str {
+        self.name.trim_start_matches("OP_SESSION_")
+    }
+
+    /// False for service accounts and the desktop app, a master password is no
+    /// help when their sessions run out
+    pub fn can_refresh(&self) -> bool {
+        match self.credential {
+            Credential::EnvVar => self.name != SERVICE_ACCOUNT_VAR,
+            Credential::RawToken => true,
+            Credential::DesktopApp => false,
+        }
+    }
+
+    /// Sign in to this session's account again, swapping in the new token
+    pub fn refresh(&mut self, password: &str) -> Result<(), OpError> {
+        if !self.can_refresh() {
+            return Err(OpError::NotSignedIn(String::from(self.account())));
+        }
+        let raw = self.credential == Credential::RawToken;
+        let mut cmd = self.op.command(false);
+        cmd.arg("signin");
+        if !self.account().is_empty() {
+            cmd.arg(format!("--account={}", self.account()));
+        }
+        if raw {
+            cmd.arg("--raw");
+        }
+        let mut child = cmd
+            .arg("-f")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(OpError::spawn)?;
+        // Dropping stdin closes it so `op` stops waiting on more input
+        child.stdin.take().unwrap().write_all(password.as_bytes())?;
+        let stdout = self.check_output(child.wait_with_output()?)?;
+        let token = if raw {
+            Some(String::from(stdout.trim())).filter(|token| !token.is_empty())
+        } else {
+            stdout.lines().find_map(Session::from_export_line).map(|session| session.token)
+        };
+        match token {
+            Some(token) => {
+                self.token = token;
+                Ok(())
+            },
+            None => Err(OpError::NotSignedIn(String::from(self.account()))),
+        }
+    }
+
+    /// Run `cmd` with this session's token and return what it printed. Failures
+    /// that mean the token expired come back as `OpError::NotSignedIn`.
+    fn run(&self, cmd: &mut Command) -> Result<String, OpError> {
+        let output = self.output(cmd)?;
+        self.check_output(output)
+    }
+
+    /// Like `run` but for output that isn't text, e.g. file attachments
+    fn run_bytes(&self, cmd: &mut Command) -> Result<Vec<u8>, OpError> {
+        let output = self.output(cmd)?;
+        self.check_status(&output)?;
+        Ok(output.stdout)
+    }
+
+    /// Like `run` with `input` written to the command's stdin
+    fn run_with_input(&self, cmd: &mut Command, input: &[u8]) -> Result<String, OpError> {
+        self.set_credential(cmd);
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(OpError::spawn)?;
+        // Dropping stdin closes it so `op` stops waiting on more input
+        child.stdin.take().unwrap().write_all(input)?;
+        self.check_output(child.wait_with_output()?)
+    }
+
+    fn output(&self, cmd: &mut Command) -> Result<Output, OpError> {
+        self.set_credential(cmd);
+        cmd.output().map_err(OpError::spawn)
+    }
+
+    fn set_credential(&self, cmd: &mut Command) {
+        match self.credential {
+            Credential::EnvVar => { cmd.env(&self.name, &self.token); },
+            // Unnamed until `name_raw_account` runs, which doesn't need the token
+            Credential::RawToken if self.name.is_empty() => {},
+            Credential::RawToken => { cmd.env(&self.name, &self.token); },
+            Credential::DesktopApp => {},
+        }
+    }
+
+    /// Stdout of a finished `op` call, or why it failed
+    fn check_output(&self, output: Output) -> Result<String, OpError> {
+        self.check_status(&output)?;
+        Ok(String::from(str::from_utf8(&output.stdout)?))
+    }
+
+    fn check_status(&self, output: &Output) -> Result<(), OpError> {
+        if !output.status.success() {
+            let stderr = String::from(String::from_utf8_lossy(&output.stderr).trim());
+            let stderr_lower = stderr.to_lowercase();
+            // Only worth asking for a password if signing in again can fix it
+            if self.can_refresh() && NOT_SIGNED_IN_ERRORS.iter().any(|e| stderr_lower.contains(e)) {
+                return Err(OpError::NotSignedIn(String::from(self.account())));
+            }
+            return Err(OpError::NonZeroExit { code: output.status.code(), stderr });
+        }
+        Ok(())
+    }
+
+    /// `op item delete`, which moves the item to the archive instead if `archive` is set
+    fn delete_or_archive_item(&self, item_name: &str, archive: bool) -> Result<(), OpError> {
+        let mut cmd = self.op.command(false);
+        cmd.arg("item")
+           .arg("delete")
+           .arg(item_name);
+        if archive {
+            cmd.arg("--archive");
+        }
+        // Nothing is printed on success so there's no json to parse
+        self.run(&mut cmd)?;
+        Ok(())
+    }
+}
+
+impl VaultBackend for Session {
+    fn list_items(&self, vault: Option<&str>) -> Result<Vec<ItemListEntry>, OpError> {
+        let mut cmd = self.op.command(true);
+        cmd.arg("item")
+           .arg("list");
+        if let Some(vault) = vault {
+            cmd.arg(format!("--vault={}", vault));
+        }
+        cmd.arg("--format=json");
+
+        Ok(serde_json::from_str(&self.run(&mut cmd)?)?)
+    }
+
+    fn list_vaults(&self) -> Result<Vec<ItemDetailsVault>, OpError> {
+        let vaults = self.run(self.op.command(true)
+                                     .arg("vault")
+                                     .arg("list")
+                                     .arg("--format=json"))?;
+
+        Ok(serde_json::from_str(&vaults)?)
+    }
+
+    fn get_item(&self, item_name: &str, vault: Option<&str>) -> Result<ItemDetails, OpError> {
+        let mut cmd = self.op.command(true);
+        cmd.arg("item")
+           .arg("get")
+           .arg(item_name);
+        if let Some(vault) = vault {
+            cmd.arg(format!("--vault={}", vault));
+        }
+        let item = self.run(cmd.arg("--format=json"))?;
+
+        Ok(serde_json::from_str(&item)?)
+    }
+
+    fn set_tags(&mut self, item_name: &str, tags: &[String]) -> Result<ItemDetails, OpError> {
+        let item = self.run(self.op.command(false)
+                                   .arg("item")
+                                   .arg("edit")
+                                   .arg(item_name)
+                                   .arg(format!("--tags={}", tags.join(",")))
+                                   .arg("--format=json"))?;
+
+        Ok(serde_json::from_str(&item)?)
+    }
+
+    fn set_favorite(&mut self, item_name: &str, favorite: bool) -> Result<ItemDetails, OpError> {
+        let item = self.run(self.op.command(false)
+                                   .arg("item")
+                                   .arg("edit")
+                                   .arg(item_name)
+                                   .arg(format!("--favorite={}", favorite))
+                                   .arg("--format=json"))?;
+
+        Ok(serde_json::from_str(&item)?)
+    }
+
+    /// Documents come from `op document get`, attachments on other items from
+    /// `op read` with a reference made of ids so names never need escaping
+    fn get_file(&self, item: &ItemDetails, file: &ItemFile) -> Result<Vec<u8>, OpError> {
+        let mut cmd = self.op.command(true);
+        if item.category == Category::Document {
+            cmd.arg("document")
+               .arg("get")
+               .arg(&item.id)
+               .arg(format!("--vault={}", item.vault.id));
         } else {
-            return Ok(Session { name, token });
+            cmd.arg("read")
+               .arg("--no-newline")
+               .arg(format!("op://{}/{}/{}", item.vault.id, item.id, file.id));
+        }
+        self.run_bytes(&mut cmd)
+    }
+
+    /// The edited item is piped to `op` as a json template like in `create_item`,
+    /// so new values, secrets included, never show up in the process list
+    fn edit_item(&mut self, item_name: &str, assignments: &[FieldAssignment]) -> Result<ItemDetails, OpError> {
+        let current = self.run(self.op.command(false)
+                                      .arg("item")
+                                      .arg("get")
+                                      .arg(item_name)
+                                      .arg("--format=json"))?;
+        let item: ItemDetails = serde_json::from_str(&current)?;
+        // Edited as plain json so fields this crate doesn't know about survive
+        let mut template: serde_json::Value = serde_json::from_str(&current)?;
+        for assignment in assignments {
+            let i = item.field_position(assignment).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("\"{}\" isn't a field", assignment.label))
+            })?;
+            template["fields"][i]["value"] = serde_json::Value::from(assignment.value.as_str());
+        }
+        let mut cmd = self.op.command(false);
+        cmd.arg("item")
+           .arg("edit")
+           .arg(&item.id)
+           .arg("--format=json");
+        let edited = self.run_with_input(&mut cmd, serde_json::to_string(&template)?.as_bytes())?;
+
+        Ok(serde_json::from_str(&edited)?)
+    }
+
+    fn get_item_template(&self, category: &str) -> Result<ItemTemplate, OpError> {
+        let template = self.run(self.op.command(true)
+                                       .arg("item")
+                                       .arg("template")
+                                       .arg("get")
+                                       .arg(category)
+                                       .arg("--format=json"))?;
+
+        Ok(serde_json::from_str(&template)?)
+    }
+
+    /// The filled in template has secrets in it so it's piped to `op` rather
+    /// than ever touching the disk
+    fn create_item(&mut self, template: &ItemTemplate, vault: Option<&str>) -> Result<ItemDetails, OpError> {
+        let mut cmd = self.op.command(false);
+        cmd.arg("item")
+           .arg("create");
+        if let Some(vault) = vault {
+            cmd.arg(format!("--vault={}", vault));
+        }
+        cmd.arg("--format=json");
+        let item = self.run_with_input(&mut cmd, serde_json::to_string(template)?.as_bytes())?;
+
+        Ok(serde_json::from_str(&item)?)
+    }
+
+    fn delete_item(&mut self, item_name: &str) -> Result<(), OpError> {
+        self.delete_or_archive_item(item_name, false)
+    }
+
+    fn archive_item(&mut self, item_name: &str) -> Result<(), OpError> {
+        self.delete_or_archive_item(item_name, true)
+    }
+
+    fn signin(&mut self, account: &str, password: &str) -> Result<(), OpError> {
+        if account != self.account() {
+            return Err(OpError::UnknownAccount(String::from(account)));
+        }
+        self.refresh(password)
+    }
+}
+
+/// Sessions for every account in the token file. Calls go to the active account,
+/// or to each account in turn when none is picked.
+#[derive(Debug)]
+pub struct Accounts {
+    pub sessions: Vec<Session>,
+    pub active: Option<usize>,
+    /// Rewritten with fresh tokens whenever an account signs in again
+    pub token_path: Option<String>,
+}
+
+impl Accounts {
+    /// Sessions for however `auth` says to sign in, token files live at `token_path`.
+    /// Every session runs `op` the way `op` says to.
+    pub fn from_auth(auth: AuthMode, token_path: String, op: &OpCommand) -> Result<Self, Box<dyn error::Error>> {
+        let (mut session, token_path) = match auth {
+            AuthMode::TokenFile => return Accounts::new(token_path, op),
+            AuthMode::RawTokenFile => {
+                let mut session = Session::new_raw(token_path.clone(), op)?;
+                session.op = op.clone();
+                session.name_raw_account()?;
+                (session, Some(token_path))
+            },
+            AuthMode::ServiceAccount => (Session::from_service_account_env()?, None),
+            AuthMode::DesktopApp => (Session::desktop_app(), None),
+        };
+        session.op = op.clone();
+        Ok(Accounts { sessions: vec![session], active: None, token_path })
+    }
+
+    /// Every account in the token file, signing in first if it's missing or expired
+    pub fn new(token_path: String, op: &OpCommand) -> Result<Self, Box<dyn error::Error>> {
+        let existing = Session::all_from_token_file(&token_path);
+        let mut sessions = match existing {
+            Ok(sessions) if Session::is_active_token_file(&token_path) => sessions,
+            _ => {
+                if existing.is_ok() {
+                    println!("Valid token found but expired, please sign in again");
+                }
+                let password = rpassword::prompt_password("Enter your 1password master password: ").unwrap();
+                Accounts::signin_to_token_file(&token_path, op, &password)?
+            },
+        };
+        for session in sessions.iter_mut() {
+            session.op = op.clone();
+        }
+        Ok(Accounts {
+            sessions,
+            active: None,
+            token_path: Some(token_path),
+        })
+    }
+
+    /// Sign in to every account already in the token file and write their new
+    /// tokens back, or to the default account if there aren't any. Accounts that
+    /// fail keep their old token and get asked for again in the app, it's only an
+    /// error if none of them sign in.
+    pub fn signin_to_token_file(token_path: &str, op: &OpCommand, password: &str) -> Result<Vec<Session>, Box<dyn error::Error>> {
+        let mut sessions = match Session::all_from_token_file(token_path) {
+            Ok(sessions) => sessions,
+            Err(_) => {
+                Session::signin(op, token_path, password)?;
+                return Session::all_from_token_file(token_path);
+            },
+        };
+        let mut signed_in = 0;
+        let mut last_err = None;
+        for session in sessions.iter_mut() {
+            session.op = op.clone();
+            match session.refresh(password) {
+                Ok(()) => signed_in += 1,
+                Err(err) => {
+                    tracing::error!("Couldn't sign in to {}: {}", session.account(), err);
+                    last_err = Some(err);
+                },
+            }
+        }
+        if let (0, Some(err)) = (signed_in, last_err) {
+            return Err(err.into());
+        }
+        Session::write_token_file(token_path, &sessions)?;
+        Ok(sessions)
+    }
+
+    fn selected(&self) -> impl Iterator<Item = &Session> {
+        let active = self.active;
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| active.is_none() || active == Some(*i))
+            .map(|(_, session)| session)
+    }
+
+    fn selected_mut(&mut self) -> impl Iterator<Item = &mut Session> {
+        let active = self.active;
+        self.sessions
+            .iter_mut()
+            .enumerate()
+            .filter(move |(i, _)| active.is_none() || active == Some(*i))
+            .map(|(_, session)| session)
+    }
+
+    /// Session new items go to, the active one or the first if using all of them
+    fn default_session(&mut self) -> &mut Session {
+        let i = self.active.unwrap_or(0);
+        &mut self.sessions[i]
+    }
+}
+
+/// Result from the first session that `f` succeeds on. Item ids are unique across
+/// accounts so at most one will have it. If none do and one of them wasn't signed
+/// in that's reported over any other error, the item is probably in that account.
+fn first_ok<S, T, F>(sessions: impl Iterator<Item = S>, mut f: F) -> Result<T, OpError>
+where
+    F: FnMut(S) -> Result<T, OpError>,
+{
+    let mut not_signed_in = None;
+    let mut last_err = None;
+    for session in sessions {
+        match f(session) {
+            Ok(res) => return Ok(res),
+            Err(err @ OpError::NotSignedIn(_)) => not_signed_in = Some(err),
+            Err(err) => last_err = Some(err),
         }
     }
-    pub fn new(token_path: String) -> Result<Self, Box<dyn error::Error>> {
-        let res = Session::from_token_file(&token_path);
-        if res.is_ok() {
-            if Session::is_active_token_file(&token_path) {
-                return res;
-            } else {
-                println!("Valid token found but expired, please sign in again");
+    // No sessions only happens with an empty token file, which fails at startup
+    Err(not_signed_in.or(last_err).unwrap_or_else(|| OpError::UnknownAccount(String::from(""))))
+}
+
+impl VaultBackend for Accounts {
+    /// A vault only exists in one account, so when listing a vault from all accounts
+    /// the others failing to find it isn't an error
+    fn list_items(&self, vault: Option<&str>) -> Result<Vec<ItemListEntry>, OpError> {
+        let mut items = Vec::new();
+        let mut last_err = None;
+        for session in self.selected() {
+            match session.list_items(vault) {
+                Ok(session_items) => {
+                    for mut item in session_items {
+                        item.account = Some(String::from(session.account()));
+                        items.push(item);
+                    }
+                },
+                Err(err @ OpError::NonZeroExit { .. }) if vault.is_some() => last_err = Some(err),
+                Err(err) => return Err(err),
             }
         }
-        Session::signin(&token_path)?;
-        return Session::from_token_file(&token_path);
+        match last_err {
+            Some(err) if items.is_empty() => Err(err),
+            _ => Ok(items),
+        }
+    }
+
+    fn list_vaults(&self) -> Result<Vec<ItemDetailsVault>, OpError> {
+        let mut vaults = Vec::new();
+        for session in self.selected() {
+            vaults.extend(session.list_vaults()?);
+        }
+        Ok(vaults)
+    }
+
+    /// A vault only exists in one account, the others fail to find it
+    fn get_item(&self, item_name: &str, vault: Option<&str>) -> Result<ItemDetails, OpError> {
+        first_ok(self.selected(), |session| session.get_item(item_name, vault))
     }
 
-    // FIXME: Refresh session
-    // pub fn refresh_session() {}
-    // FIXME: if the tui is open
-    // pub fn auto_refresh_session() {}
+    fn get_file(&self, item: &ItemDetails, file: &ItemFile) -> Result<Vec<u8>, OpError> {
+        first_ok(self.selected(), |session| session.get_file(item, file))
+    }
+
+    fn set_tags(&mut self, item_name: &str, tags: &[String]) -> Result<ItemDetails, OpError> {
+        first_ok(self.selected_mut(), |session| session.set_tags(item_name, tags))
+    }
+
+    fn set_favorite(&mut self, item_name: &str, favorite: bool) -> Result<ItemDetails, OpError> {
+        first_ok(self.selected_mut(), |session| session.set_favorite(item_name, favorite))
+    }
+
+    fn edit_item(&mut self, item_name: &str, assignments: &[FieldAssignment]) -> Result<ItemDetails, OpError> {
+        first_ok(self.selected_mut(), |session| session.edit_item(item_name, assignments))
+    }
+
+    fn get_item_template(&self, category: &str) -> Result<ItemTemplate, OpError> {
+        self.sessions[self.active.unwrap_or(0)].get_item_template(category)
+    }
 
-    // FIXME: instead of using serde_json::Error, use enum that also can be `Box<dyn error::Error>`
-    pub fn list_items(&self) -> Result<Vec<ItemListEntry>, serde_json::Error> {
-        let output = Command::new("op")
-                             .env(&self.name, &self.token)
-                             .arg("item")
-                             .arg("list")
-                             .arg("--format=json")
-                             .arg("--cache")
-                             .output().unwrap();
-        let items = str::from_utf8(&output.stdout).unwrap();
+    /// New items go to the active account, or the first one if listing all of them.
+    /// A vault only exists in one account so with one given, it's whichever has it.
+    fn create_item(&mut self, template: &ItemTemplate, vault: Option<&str>) -> Result<ItemDetails, OpError> {
+        match vault {
+            Some(vault) => first_ok(self.selected_mut(), |session| session.create_item(template, Some(vault))),
+            None => self.default_session().create_item(template, None),
+        }
+    }
 
-        serde_json::from_str(items)
+    fn delete_item(&mut self, item_name: &str) -> Result<(), OpError> {
+        first_ok(self.selected_mut(), |session| session.delete_item(item_name))
+    }
 
+    fn archive_item(&mut self, item_name: &str) -> Result<(), OpError> {
+        first_ok(self.selected_mut(), |session| session.archive_item(item_name))
     }
 
-    pub fn get_item(&self, item_name: &String) -> Result<ItemDetails, serde_json::Error> {
-        let output = Command::new("op")
-                             .env(&self.name, &self.token)
-                             .arg("item")
-                             .arg("get")
-                             .arg(item_name)
-                             .arg("--format=json")
-                             .arg("--cache")
-                             .output().unwrap();
-        let items = str::from_utf8(&output.stdout).unwrap();
+    fn accounts(&self) -> Vec<String> {
+        self.sessions.iter().map(|session| String::from(session.account())).collect()
+    }
 
-        serde_json::from_str(items)
+    fn set_account(&mut self, account: Option<&str>) -> Result<(), OpError> {
+        self.active = match account {
+            Some(account) => Some(
+                self.sessions
+                    .iter()
+                    .position(|session| session.account() == account)
+                    .ok_or_else(|| OpError::UnknownAccount(String::from(account)))?
+            ),
+            None => None,
+        };
+        Ok(())
+    }
 
+    fn signin(&mut self, account: &str, password: &str) -> Result<(), OpError> {
+        let session = self.sessions
+            .iter_mut()
+            .find(|session| session.account() == account)
+            .ok_or_else(|| OpError::UnknownAccount(String::from(account)))?;
+        session.refresh(password)?;
+        if let Some(token_path) = &self.token_path {
+            if let Err(err) = Session::write_token_file(token_path, &self.sessions) {
+                tracing::error!("Couldn't save refreshed token to {}: {}", token_path, err);
+            }
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,96 @@
+/// `:run`, commands started from the item view with the item's fields in their environment
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use super::app_config::RunEnv;
+use super::op::{Category, ItemDetails};
+
+/// Command waiting for the draw loop to hand it the terminal
+#[derive(Debug, Clone)]
+pub struct PendingRun {
+    pub command: String,
+    /// Variable names and values, never logged
+    pub env: Vec<(String, String)>,
+}
+
+impl PendingRun {
+    /// Run the command with `sh -c` in the foreground and wait for it to finish
+    pub fn status(&self) -> io::Result<ExitStatus> {
+        Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .envs(self.env.iter().map(|(name, value)| (name, value)))
+            .status()
+    }
+}
+
+/// Every non-empty field of `item` as an environment variable. Names come from
+/// `run_env` for the item, then for its category, then from the field's label
+/// with its section in front, e.g. `SECURITY_QUESTION_1_ANSWER`. OTP fields are
+/// exported as the current code.
+pub fn item_env(item: &ItemDetails, run_env: &RunEnv) -> Vec<(String, String)> {
+    let item_names = run_env.items.get(&item.title).or_else(|| run_env.items.get(&item.id));
+    let category_names = run_env.categories
+        .iter()
+        .find(|(category, _)| Category::from(category.as_str()) == item.category)
+        .map(|(_, names)| names);
+    let mut env = Vec::with_capacity(item.fields.len());
+    for field in item.fields.iter() {
+        let value = match (field.totp(), &field.value) {
+            (Some(totp), _) => totp.now().0,
+            (None, Some(value)) if !value.is_empty() => value.clone(),
+            _ => continue,
+        };
+        let configured = [item_names, category_names].iter().flatten().find_map(|names| {
+            field.label.as_ref().and_then(|label| names.get(label))
+                .or_else(|| field.id.as_ref().and_then(|id| names.get(id)))
+        });
+        let name = match configured {
+            Some(name) => name.clone(),
+            None => {
+                let label = field.label.as_deref().or(field.id.as_deref()).unwrap_or_default();
+                match item.section_of(field).and_then(|section| section.heading()) {
+                    Some(section) => env_name(&format!("{} {}", section, label)),
+                    None => env_name(label),
+                }
+            },
+        };
+        if name.is_empty() {
+            continue;
+        }
+        // Later fields don't clobber earlier ones with the same name
+        if !env.iter().any(|(existing, _): &(String, String)| existing == &name) {
+            env.push((name, value));
+        }
+    }
+    env
+}
+
+/// `label` as a shell friendly variable name, e.g. "one-time password" becomes
+/// `ONE_TIME_PASSWORD`
+pub fn env_name(label: &str) -> String {
+    let mut name = String::with_capacity(label.len());
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_end_matches('_');
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        String::from(name)
+    }
+}
+
+#[test]
+fn env_name_test() {
+    assert_eq!("PASSWORD", env_name("password"));
+    assert_eq!("ONE_TIME_PASSWORD", env_name("one-time password"));
+    assert_eq!("SECURITY_QUESTION_1_ANSWER", env_name("Security question 1 answer"));
+    assert_eq!("API_KEY_V2", env_name("  api key (v2)!"));
+    assert_eq!("_2FA_CODE", env_name("2fa code"));
+    assert_eq!("", env_name("☃"));
+}
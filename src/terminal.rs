@@ -26,6 +26,24 @@ impl TerminalModifier {
         let terminal = Terminal::new(backend).unwrap();
         Ok(TerminalModifier {terminal})
     }
+
+    /// Give the terminal back to the shell for a while, e.g. to run a command in it
+    pub fn suspend(&mut self) -> Result<(), Box<dyn Error>> {
+        disable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
+        self.terminal.show_cursor()?;
+        tracing::info!("Left the alternate screen");
+        Ok(())
+    }
+
+    /// Take the terminal back after `suspend`, the next draw repaints everything
+    pub fn resume(&mut self) -> Result<(), Box<dyn Error>> {
+        enable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), EnterAlternateScreen)?;
+        self.terminal.clear()?;
+        tracing::info!("Back on the alternate screen");
+        Ok(())
+    }
 }
 
 impl Drop for TerminalModifier {
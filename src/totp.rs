@@ -0,0 +1,135 @@
+/// RFC 6238 one-time passwords, computed from the `otpauth://` URIs 1password stores
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::percent_decode_str;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Everything needed to generate codes, the defaults match what authenticator
+/// apps assume when the URI leaves a parameter out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Totp {
+    pub secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    /// Seconds each code is valid for
+    pub period: u64,
+}
+
+impl Totp {
+    /// Parse an `otpauth://totp/...?secret=...` URI, or a bare base32 secret
+    /// since 1password lets you paste one of those in too
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let uri = uri.trim();
+        let query = match uri.strip_prefix("otpauth://") {
+            Some(rest) => {
+                if !rest.to_lowercase().starts_with("totp/") {
+                    return None;
+                }
+                rest.split_once('?')?.1
+            },
+            None => return Some(Totp::new(decode_secret(uri)?)),
+        };
+        let mut totp = Totp::new(Vec::new());
+        for param in query.split('&') {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode_str(value).decode_utf8().ok()?;
+            match key.to_lowercase().as_str() {
+                "secret" => totp.secret = decode_secret(&value)?,
+                "algorithm" => totp.algorithm = match value.to_uppercase().as_str() {
+                    "SHA1" => Algorithm::Sha1,
+                    "SHA256" => Algorithm::Sha256,
+                    "SHA512" => Algorithm::Sha512,
+                    _ => return None,
+                },
+                "digits" => totp.digits = value.parse().ok().filter(|d| (1..=10).contains(d))?,
+                "period" => totp.period = value.parse().ok().filter(|p| *p > 0)?,
+                _ => {},
+            }
+        }
+        if totp.secret.is_empty() {
+            None
+        } else {
+            Some(totp)
+        }
+    }
+
+    fn new(secret: Vec<u8>) -> Self {
+        Totp { secret, algorithm: Algorithm::Sha1, digits: 6, period: 30 }
+    }
+
+    /// Code for `unix_time` seconds since the epoch, zero padded to `digits`
+    pub fn code_at(&self, unix_time: u64) -> String {
+        let counter = (unix_time / self.period).to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => hmac::<Hmac<Sha1>>(&self.secret, &counter),
+            Algorithm::Sha256 => hmac::<Hmac<Sha256>>(&self.secret, &counter),
+            Algorithm::Sha512 => hmac::<Hmac<Sha512>>(&self.secret, &counter),
+        };
+        // Dynamic truncation from RFC 4226 section 5.3
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+        let code = u64::from(binary) % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Current code and how many seconds it's still good for
+    pub fn now(&self) -> (String, u64) {
+        let unix_time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        (self.code_at(unix_time), self.period - unix_time % self.period)
+    }
+}
+
+/// Base32 secrets are often written lowercase, with spaces or with padding
+fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    BASE32_NOPAD.decode(secret.as_bytes()).ok().filter(|secret| !secret.is_empty())
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    // HMAC takes keys of any length so this can't fail
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key).unwrap();
+    mac.update(msg);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[test]
+fn rfc_6238_test_vectors() {
+    // Seeds from RFC 6238 appendix B, base32 encoded
+    let sha1 = Totp::from_uri("otpauth://totp/x?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8").unwrap();
+    let sha256 = Totp::from_uri(
+        "otpauth://totp/x?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA&digits=8&algorithm=SHA256",
+    ).unwrap();
+    let sha512 = Totp::from_uri(
+        "otpauth://totp/x?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA&digits=8&algorithm=SHA512",
+    ).unwrap();
+    assert_eq!(sha1.code_at(59), "94287082");
+    assert_eq!(sha256.code_at(59), "46119246");
+    assert_eq!(sha512.code_at(59), "90693936");
+    assert_eq!(sha1.code_at(1111111109), "07081804");
+    assert_eq!(sha256.code_at(1234567890), "91819424");
+    assert_eq!(sha512.code_at(20000000000), "47863826");
+}
+
+#[test]
+fn totp_from_uri_test() {
+    let totp = Totp::from_uri("otpauth://totp/AWS:root%40example.com?secret=gezd%20gnbv&issuer=AWS&period=60").unwrap();
+    assert_eq!(Totp { secret: b"12345".to_vec(), algorithm: Algorithm::Sha1, digits: 6, period: 60 }, totp);
+    assert_eq!(Some(b"12345".to_vec()), Totp::from_uri("GEZDGNBV").map(|t| t.secret));
+    assert_eq!(None, Totp::from_uri("otpauth://hotp/x?secret=GEZDGNBV"));
+    assert_eq!(None, Totp::from_uri("otpauth://totp/x?issuer=AWS"));
+    assert_eq!(None, Totp::from_uri("otpauth://totp/x?secret=GEZDGNBV&algorithm=MD5"));
+    assert_eq!(None, Totp::from_uri("not a secret"));
+}
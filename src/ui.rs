@@ -1,16 +1,27 @@
 /// Namespace for creating rust-tui components
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
 use std::cmp;
 use tui::{
+    layout::Rect,
     style::{Color,Style},
     text::Span,
     widgets::{Cell, Row},
 };
+use super::dates;
 use super::op;
 
+/// Marks favorites in the item list and item view
+pub static FAVORITE: &str = "★";
+
 /// Given a vec of column display names, return a tui Row object
-pub fn new_header_row<'a>(headers: &'a Vec<String>) -> Row<'a> {
+pub fn new_header_row(headers: &[String]) -> Row<'_> {
     let header_cells = headers
         .iter()
+        .map(|h| match h.as_str() {
+            "favorite" => FAVORITE,
+            h => h,
+        })
         .map(|h| Cell::from(Span::raw(h)).style(Style::default().fg(Color::Red)));
     Row::new(header_cells)
         .style(Style::default().bg(Color::Blue))
@@ -18,10 +29,147 @@ pub fn new_header_row<'a>(headers: &'a Vec<String>) -> Row<'a> {
         .bottom_margin(1)
 }
 
+static SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Table title, with a spinner appended while background work is running
+pub fn loading_title(title: &str, is_loading: bool, tick: usize) -> String {
+    if is_loading {
+        format!("{} {} loading…", title, SPINNER_FRAMES[tick % SPINNER_FRAMES.len()])
+    } else {
+        String::from(title)
+    }
+}
+
+/// Value of a field being edited with a trailing cursor, concealed values are masked
+pub fn edit_value(input: &str, is_concealed: bool) -> String {
+    let mut value = if is_concealed {
+        "*".repeat(input.chars().count())
+    } else {
+        String::from(input)
+    };
+    value.push('█');
+    value
+}
+
+/// Current code of an OTP field with how long it has left. The draw loop
+/// redraws every tick so the countdown and code stay current.
+pub fn otp_value(code: &str, seconds_left: u64) -> String {
+    format!("{} ({}s left)", code, seconds_left)
+}
+
+/// Title of the item view, starred for favorites and with the item's tags if it has any
+pub fn item_title(item: &op::ItemDetails) -> String {
+    let mut title = String::from("Entry");
+    if item.favorite {
+        title.push(' ');
+        title.push_str(FAVORITE);
+    }
+    if !item.tags.is_empty() {
+        title.push_str(&format!(" [tags: {}]", item.tags.join(", ")));
+    }
+    title
+}
+
+/// One line overview of a credit card or SSH key, `None` for other categories or
+/// when the fields it's made from are empty
+pub fn item_summary(item: &op::ItemDetails) -> Option<String> {
+    let value = |id: &str| {
+        item.fields
+            .iter()
+            .find(|field| field.id.as_deref() == Some(id))
+            .and_then(|field| field.value.as_deref())
+            .filter(|value| !value.is_empty())
+    };
+    match item.category {
+        op::Category::CreditCard => {
+            let number: String = value("ccnum")?.chars().filter(|c| c.is_ascii_digit()).collect();
+            let last4 = &number[number.len().saturating_sub(4)..];
+            let mut summary = format!("{} •••• {}", value("type").unwrap_or("card"), last4);
+            if let Some(expiry) = value("expiry") {
+                summary.push_str(&format!(", expires {}", month_year(expiry)));
+            }
+            if let Some(cardholder) = value("cardholder") {
+                summary.push_str(&format!(", {}", cardholder));
+            }
+            Some(summary)
+        },
+        op::Category::SshKey => {
+            let key_type = value("key_type");
+            let fingerprint = value("fingerprint");
+            match (key_type, fingerprint) {
+                (None, None) => None,
+                _ => Some(format!("{} {}", key_type.unwrap_or("key"), fingerprint.unwrap_or("")).trim_end().to_string()),
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Value of a field as shown in the item view. Card numbers are split into
+/// groups of 4, expiry dates read `MM/YYYY` and private keys are cut down to
+/// their first line.
+pub fn field_value(field: &op::ItemDetailsField) -> String {
+    let value = field.value.clone().unwrap_or_default();
+    match field.r#type.as_deref() {
+        Some("CREDIT_CARD_NUMBER") => {
+            let digits: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+            digits.chunks(4).map(|chunk| chunk.iter().collect::<String>()).collect::<Vec<String>>().join(" ")
+        },
+        Some("MONTH_YEAR") => month_year(&value),
+        Some("SSHKEY") if value.lines().count() > 1 => {
+            format!("{} ({} lines, `y` to copy)", value.lines().next().unwrap_or_default(), value.lines().count())
+        },
+        _ => value,
+    }
+}
+
+/// `op` stores month/year fields as `YYYYMM`
+fn month_year(value: &str) -> String {
+    if value.len() == 6 && value.chars().all(|c| c.is_ascii_digit()) {
+        format!("{}/{}", &value[4..], &value[..4])
+    } else {
+        String::from(value)
+    }
+}
+
+/// Heading of a section in the item view, the arrow shows whether it's collapsed
+pub fn section_heading(label: &str, collapsed: bool) -> String {
+    format!("{} {}", if collapsed { '▸' } else { '▾' }, label)
+}
+
+/// Size of an attachment with a hint on how to get at it
+pub fn file_value(size: u64) -> String {
+    let size = if size < 1024 {
+        format!("{} B", size)
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    };
+    format!("attachment, {} (`s` to save)", size)
+}
+
+/// Toggle in a popup along with the key that flips it, e.g. `[x] a`
+pub fn checkbox(key: char, checked: bool) -> String {
+    format!("[{}] {}", if checked { 'x' } else { ' ' }, key)
+}
+
+/// Area `percent_x` wide and `height` rows tall in the middle of `r`, for popups
+pub fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let width = r.width * percent_x / 100;
+    let height = cmp::min(height, r.height);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
+}
+
 #[allow(dead_code)]
-fn scramble_string(s_old: &String) -> String {
+fn scramble_string(s_old: &str) -> String {
     let mut s_new = String::with_capacity(s_old.len());
-    for c_old in s_old.as_str().chars() {
+    for c_old in s_old.chars() {
         s_new.push({
             let charset = match c_old {
                 'a' | 'e' | 'i' | 'o' | 'u' => "aeiou",
@@ -40,26 +188,37 @@ fn scramble_string(s_old: &String) -> String {
     s_new
 }
 
-pub fn new_item_list_row<'a, 'b>(item: &'a op::ItemListEntry, headers: &'b Vec<String>) -> Row<'a> {
+pub fn new_item_list_row<'a>(item: &'a op::ItemListEntry, headers: &[String], date_format: &str, now: &DateTime<Utc>) -> Row<'a> {
     let mut height = 1;
     let cells = headers.iter().map(|header| {
-        let val = match header.as_str() {
-            "id" => &item.id,
-            "title" => &item.title,
-            "updated_at" => &item.updated_at,
+        let val: Cow<str> = match header.as_str() {
+            "id" => Cow::from(&item.id),
+            "title" => Cow::from(&item.title),
+            "created_at" => Cow::from(dates::format(&item.created_at, date_format, now)),
+            "updated_at" => Cow::from(dates::format(&item.updated_at, date_format, now)),
+            "vault" => Cow::from(&item.vault.name),
+            "tags" => Cow::from(item.tags.join(", ")),
+            "category" => Cow::from(item.category.name()),
+            "favorite" => Cow::from(if item.favorite { FAVORITE } else { "" }),
             "additional_information" => {
                 match &item.additional_information {
-                    Some(a_info) => a_info.as_str(),
-                    None => ""
+                    Some(a_info) => Cow::from(a_info),
+                    None => Cow::from("")
                 }
             },
             "hrefs" => {
                 match &item.hrefs {
-                    Some(hrefs) => hrefs.as_str(),
-                    None => ""
+                    Some(hrefs) => Cow::from(hrefs),
+                    None => Cow::from("")
+                }
+            },
+            "account" => {
+                match &item.account {
+                    Some(account) => Cow::from(account),
+                    None => Cow::from("")
                 }
             },
-            _ => "",
+            _ => Cow::from(""),
         };
         height = cmp::max(height, val.chars().filter(|c| *c == '\n').count());
         Cell::from(Span::raw(val))
@@ -1,28 +1,90 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::path::Path;
 
 /// Arg `idx` incremented by 1, if past `max`, return 0
 pub fn inc_or_wrap(idx: usize, max: usize) -> usize {
     if (max == 0) || (idx >= max - 1) {
-        return 0;
+        0
     } else {
-        return idx + 1;
+        idx + 1
     }
 }
 
 /// Arg `idx` decremented by 1, if -1, return `max`
 pub fn dec_or_wrap(idx: usize, max: usize) -> usize {
     if max == 0  {
-        return 0;
+        0
     } else if idx == 0 {
-        return max - 1;
+        max - 1
     } else {
-        return idx - 1;
+        idx - 1
     }
 }
 
 /// Returns true if file path exists, else false
-pub fn file_exists(fp: &String) -> bool {
-    return Path::new(fp.as_str()).is_file();
+pub fn file_exists(fp: &str) -> bool {
+    Path::new(fp).is_file()
+}
+
+/// Split `s` into arguments on whitespace like a shell does, so paths with spaces
+/// can be given in single or double quotes or with backslashes. `None` if a quote
+/// isn't closed or there's nothing after a backslash.
+pub fn split_args(s: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            },
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\') => arg.get_or_insert_with(String::new).push(chars.next()?),
+            (_, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    args.extend(arg);
+    Some(args)
+}
+
+/// Write `contents` to `fp` so only the current user can read it (0600),
+/// replacing whatever was there before
+pub fn write_private_file(fp: &str, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(fp)?;
+    // `mode` only applies when the file is newly created
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)
+}
+
+/// `fp` with a leading `~` swapped for `$HOME`
+pub fn expand_home(fp: &str) -> String {
+    match (fp.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => String::from(fp),
+    }
+}
+
+/// Message out of an `op` error line, without the `[ERROR] 2022/01/01 12:00:00 ` log prefix
+pub fn trim_op_log_prefix(stderr: &str) -> &str {
+    let stderr = stderr.trim();
+    match stderr.strip_prefix("[ERROR] ") {
+        // Skip the date and time
+        Some(rest) => rest.splitn(3, ' ').nth(2).unwrap_or(rest),
+        None => stderr,
+    }
 }
 
 #[test]
@@ -41,6 +103,45 @@ fn dec_or_wrap_test() {
 
 #[test]
 fn file_exists_test() {
-    assert_eq!(true, file_exists(&String::from("Cargo.toml")));
-    assert_eq!(false, file_exists(&String::from("doesnt-exist.toml")));
+    assert!(file_exists(&String::from("Cargo.toml")));
+    assert!(!file_exists(&String::from("doesnt-exist.toml")));
+}
+
+#[test]
+fn write_private_file_test() {
+    let fp = std::env::temp_dir().join("tui-1password-write-private-file-test");
+    let fp = fp.to_str().unwrap();
+    write_private_file(fp, b"first").unwrap();
+    fs::set_permissions(fp, fs::Permissions::from_mode(0o644)).unwrap();
+    write_private_file(fp, b"second").unwrap();
+    assert_eq!("second", fs::read_to_string(fp).unwrap());
+    assert_eq!(0o600, fs::metadata(fp).unwrap().permissions().mode() & 0o777);
+    fs::remove_file(fp).unwrap();
+}
+
+#[test]
+fn expand_home_test() {
+    let home = std::env::var("HOME").unwrap();
+    assert_eq!(format!("{}/x.pem", home), expand_home("~/x.pem"));
+    assert_eq!(home, expand_home("~"));
+    assert_eq!("~other/x.pem", expand_home("~other/x.pem"));
+    assert_eq!("x.pem", expand_home("x.pem"));
+}
+
+#[test]
+fn split_args_test() {
+    assert_eq!(Some(vec![String::from("a.env"), String::from("b.env")]), split_args(" a.env  b.env "));
+    assert_eq!(
+        Some(vec![String::from("my template.env"), String::from("it's out.env"), String::from("x y")]),
+        split_args("\"my template.env\" 'it'\\''s out.env' x\\ y"),
+    );
+    assert_eq!(Some(vec![String::new()]), split_args("''"));
+    assert_eq!(None, split_args("\"a.env b.env"));
+    assert_eq!(None, split_args("a.env\\"));
+}
+
+#[test]
+fn trim_op_log_prefix_test() {
+    assert_eq!(trim_op_log_prefix("[ERROR] 2022/05/01 12:00:00 \"x\" isn't an item\n"), "\"x\" isn't an item");
+    assert_eq!(trim_op_log_prefix("unknown command"), "unknown command");
 }
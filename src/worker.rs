@@ -0,0 +1,132 @@
+/// Runs backend calls off the UI thread
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use super::app::ListScope;
+use super::backend::VaultBackend;
+use super::err::{InjectError, OpError};
+use super::op;
+
+/// Results sent back from a backend call, matched up with the request that started it
+// Only a handful of these are ever alive at once, not worth boxing
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum Response {
+    /// Scope the list was asked for along with the result
+    Items(ListScope, Result<Vec<op::ItemListEntry>, OpError>),
+    /// Item id or title that was requested along with the result
+    ItemDetails(String, Result<op::ItemDetails, OpError>),
+    ItemTemplate(Result<op::ItemTemplate, OpError>),
+    ItemCreated(Result<op::ItemDetails, OpError>),
+    Vaults(Result<Vec<op::ItemDetailsVault>, OpError>),
+    /// Account that was switched to, `None` for all accounts
+    Account(Result<Option<String>, OpError>),
+    /// Id of the item that was deleted or archived
+    ItemRemoved(String, Result<(), OpError>),
+    /// Account that was signed in to again
+    Signin(String, Result<(), OpError>),
+    /// Item changed without opening it, e.g. tagged, with the message to show once it's done
+    ItemUpdated(String, Result<op::ItemDetails, OpError>),
+    /// Path an attachment was written to
+    FileSaved(String, Result<(), OpError>),
+    /// Path items were exported to along with how many there were
+    Exported(String, Result<usize, OpError>),
+    /// Path a template was rendered to along with how many references it had
+    Injected(String, Result<usize, InjectError>),
+}
+
+impl Response {
+    /// Error the request failed with, if it did
+    pub fn error(&self) -> Option<&OpError> {
+        match self {
+            Response::Items(_, res) => res.as_ref().err(),
+            Response::ItemDetails(_, res) => res.as_ref().err(),
+            Response::ItemTemplate(res) => res.as_ref().err(),
+            Response::ItemCreated(res) => res.as_ref().err(),
+            Response::Vaults(res) => res.as_ref().err(),
+            Response::Account(res) => res.as_ref().err(),
+            Response::ItemRemoved(_, res) => res.as_ref().err(),
+            Response::ItemUpdated(_, res) => res.as_ref().err(),
+            Response::FileSaved(_, res) => res.as_ref().err(),
+            Response::Exported(_, res) => res.as_ref().err(),
+            Response::Injected(_, Err(InjectError::Op(err))) => Some(err),
+            Response::Injected(..) => None,
+            // Failing to sign in is handled by asking again, never retried
+            Response::Signin(..) => None,
+        }
+    }
+}
+
+/// A backend call, kept around so it can be run again after signing back in
+pub type Job<V> = Arc<dyn Fn(&mut V) -> Response + Send + Sync>;
+
+/// Owns the backend and runs requests against it one at a time on a single
+/// background thread, in the order they were made. Results come back in `try_recv`.
+pub struct Worker<V: VaultBackend> {
+    pub session: Arc<Mutex<V>>,
+    jobs: Sender<Job<V>>,
+    rx: Receiver<(Job<V>, Option<Response>)>,
+    pending: usize,
+}
+
+impl<V: VaultBackend> Worker<V> {
+    pub fn new(session: V) -> Self {
+        let session = Arc::new(Mutex::new(session));
+        let (jobs, job_rx) = mpsc::channel::<Job<V>>();
+        let (tx, rx) = mpsc::channel();
+        let backend = Arc::clone(&session);
+        // Runs until the worker is dropped and there's nothing left to send jobs
+        thread::spawn(move || {
+            for job in job_rx {
+                // Only poisoned if something else panicked while holding it, the
+                // backend is still fine to use
+                let mut backend = backend.lock().unwrap_or_else(PoisonError::into_inner);
+                // A job that panics gets no response but mustn't take the thread
+                // down with it, nothing queued after it would ever run
+                let response = panic::catch_unwind(AssertUnwindSafe(|| job(&mut backend))).ok();
+                drop(backend);
+                // Only fails if the app has already shut down, nothing to report to
+                if tx.send((job, response)).is_err() {
+                    break;
+                }
+            }
+        });
+        Worker { session, jobs, rx, pending: 0 }
+    }
+
+    /// Queue `f` to run against the backend on the background thread
+    pub fn spawn<F>(&mut self, f: F)
+    where
+        F: Fn(&mut V) -> Response + Send + Sync + 'static,
+    {
+        self.spawn_job(Arc::new(f));
+    }
+
+    /// Run a job again, e.g. one that failed because the session expired
+    pub fn spawn_job(&mut self, job: Job<V>) {
+        if self.jobs.send(job).is_ok() {
+            self.pending += 1;
+        }
+    }
+
+    /// Next finished response along with the job that produced it, never blocks.
+    /// Jobs that panicked are logged and skipped.
+    pub fn try_recv(&mut self) -> Option<(Job<V>, Response)> {
+        // Nothing finished yet, can't be disconnected while the thread holds a sender
+        while let Ok((job, response)) = self.rx.try_recv() {
+            self.pending -= 1;
+            match response {
+                Some(response) => return Some((job, response)),
+                None => tracing::error!("Backend call panicked, dropped it"),
+            }
+        }
+        None
+    }
+
+    /// True while any request hasn't returned yet
+    pub fn is_busy(&self) -> bool {
+        self.pending > 0
+    }
+}